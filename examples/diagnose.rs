@@ -0,0 +1,7 @@
+//! Example CLI app that prints a health report on the Secret Service,
+//! for use when the store doesn't work on some machine.
+use zbus_secret_service_keyring_store::Store;
+
+fn main() {
+    print!("{}", Store::diagnose());
+}
@@ -0,0 +1,174 @@
+/*!
+
+Encrypted export and import of items.
+
+[Store::export](crate::Store::export) writes matching items to an archive
+that is encrypted with a key derived from a passphrase, and
+[Store::import](crate::Store::import) recreates the archived items in
+(possibly) another Secret Service. Each archived item records its label,
+attributes, collection, secret content type, and secret.
+
+The archive format is versioned: the version is part of the archive's
+header and is checked on import.
+
+*/
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+use serde::{Deserialize, Serialize};
+
+use keyring_core::{Error, Result};
+
+use crate::crypto::{SealingKey, salt_of};
+use crate::service::Service;
+
+pub(crate) const MAGIC: &[u8; 8] = b"KRSSAR01";
+const VERSION: u32 = 1;
+
+/// What to do when an imported item is already present.
+///
+/// An item is already present if the collection it's being imported
+/// into has an item with all the same attributes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Leave the existing item alone and don't import the archived one.
+    Skip,
+    /// Replace the secret and label of the existing item with the archived ones.
+    Overwrite,
+    /// Create the archived item alongside the existing one.
+    KeepBoth,
+}
+
+/// The outcome of an import.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Items created from the archive.
+    pub created: usize,
+    /// Existing items overwritten from the archive.
+    pub overwritten: usize,
+    /// Archived items skipped because they were already present.
+    pub skipped: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Archive {
+    version: u32,
+    items: Vec<ArchivedItem>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ArchivedItem {
+    label: String,
+    attributes: HashMap<String, String>,
+    collection: String,
+    content_type: String,
+    secret: Vec<u8>,
+}
+
+/// Write an archive of the matching items, returning how many were archived.
+///
+/// If a collection is given, only that collection is searched.
+pub(crate) fn export(
+    ss: &Service,
+    spec: &HashMap<&str, &str>,
+    collection: Option<&str>,
+    passphrase: &str,
+    writer: &mut dyn Write,
+) -> Result<usize> {
+    let paths = match collection {
+        Some(c) => ss.find_matching_items_in(c, spec)?,
+        None => ss.find_matching_items(spec)?,
+    };
+    let mut items = Vec::with_capacity(paths.len());
+    for path in paths {
+        ss.ensure_unlocked(&path)?;
+        items.push(ArchivedItem {
+            label: ss.get_label(&path)?,
+            attributes: ss.get_attributes(&path)?,
+            collection: ss.get_collection_name(&path)?,
+            content_type: ss.get_content_type(&path)?,
+            secret: ss.get_secret(&path)?,
+        });
+    }
+    let count = items.len();
+    let archive = Archive {
+        version: VERSION,
+        items,
+    };
+    let plain = serde_json::to_vec(&archive).map_err(|e| Error::PlatformFailure(e.into()))?;
+    let sealed = SealingKey::new(passphrase)?.seal(MAGIC, &plain)?;
+    writer
+        .write_all(&sealed)
+        .map_err(|e| Error::PlatformFailure(Box::new(e)))?;
+    Ok(count)
+}
+
+/// Recreate the items in an archive, resolving conflicts per the policy.
+pub(crate) fn import(
+    ss: &Service,
+    reader: &mut dyn Read,
+    passphrase: &str,
+    policy: ConflictPolicy,
+) -> Result<ImportReport> {
+    let mut sealed = Vec::new();
+    reader
+        .read_to_end(&mut sealed)
+        .map_err(|e| Error::PlatformFailure(Box::new(e)))?;
+    let key = SealingKey::with_salt(passphrase, salt_of(MAGIC, &sealed)?)?;
+    let plain = key.open(MAGIC, &sealed)?;
+    // the decrypted data holds every archived secret, so neither it nor
+    // the parse error (which can quote it) is put in the error
+    let archive: Archive = serde_json::from_slice(&plain).map_err(|e| {
+        Error::BadDataFormat(
+            Vec::new(),
+            format!(
+                "The archive is malformed (at line {}, column {})",
+                e.line(),
+                e.column()
+            )
+            .into(),
+        )
+    })?;
+    if archive.version != VERSION {
+        return Err(Error::BadDataFormat(
+            Vec::new(),
+            format!("Unsupported archive version {}", archive.version).into(),
+        ));
+    }
+    let mut report = ImportReport::default();
+    for item in archive.items {
+        let attributes: HashMap<&str, &str> = item
+            .attributes
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        let existing = match ss.find_matching_items_in(&item.collection, &attributes) {
+            Ok(paths) => paths,
+            Err(Error::NoEntry) => Vec::new(),
+            Err(err) => return Err(err),
+        };
+        match policy {
+            ConflictPolicy::Skip if !existing.is_empty() => report.skipped += 1,
+            ConflictPolicy::Overwrite if !existing.is_empty() => {
+                for path in existing {
+                    ss.set_secret(&path, &item.secret, &item.content_type)?;
+                    ss.set_label(&path, &item.label)?;
+                    report.overwritten += 1;
+                }
+            }
+            _ => {
+                ss.create_item(
+                    &item.collection,
+                    &item.label,
+                    attributes,
+                    &item.secret,
+                    &item.content_type,
+                    false,
+                )?;
+                report.created += 1;
+            }
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,123 @@
+/*!
+
+Batched operations on many entries.
+
+[Store::get_secrets](crate::Store::get_secrets) and
+[Store::set_secrets](crate::Store::set_secrets) read and write the secrets of
+many entries at once. The entries are named by service and user, and are
+treated as if they had been built with no modifiers. Each operation returns
+a result for each entry, in the order the entries were given, and per-entry
+failures (such as a missing or ambiguous entry) don't stop the batch.
+
+A batch holds the store's connection to the Secret Service for each of its
+phases rather than for each call, and it unlocks all the locked items it finds
+with a single call (and so at most one prompt), rather than one per item.
+
+The Secret Service's search can't match any one of several attribute sets,
+so a batch still makes one search per entry. The Secret Service also has a
+`GetSecrets` call that returns many secrets in one message, but the
+secret-service crate that this crate is built on doesn't expose it (nor
+the encrypted session it would need), so secrets are still transferred one
+item at a time.
+
+*/
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use zbus::zvariant::OwnedObjectPath;
+
+use keyring_core::api::CredentialApi;
+use keyring_core::{Entry, Error, Result};
+
+use crate::cred::{Specifier, Wrapper, check_expiry};
+use crate::profile::Profile;
+use crate::service::{DEFAULT_CONTENT_TYPE, Service};
+
+/// Read the secrets of the given entries.
+pub(crate) fn get_secrets(
+    ss: &Arc<Service<'static>>,
+    entries: &[(&str, &str)],
+) -> Result<Vec<Result<Vec<u8>>>> {
+    let found = find_unique_items(ss, entries)?;
+    let paths: Vec<OwnedObjectPath> = found
+        .iter()
+        .filter_map(|r| r.as_ref().ok().cloned())
+        .collect();
+    let mut fetched = ss.get_secrets_batch(&paths).into_iter();
+    let results = found
+        .into_iter()
+        .map(|r| {
+            r?;
+            let (attributes, secret) = fetched.next().expect("one result per path")?;
+            check_expiry(&attributes)?;
+            Ok(secret)
+        })
+        .collect();
+    Ok(results)
+}
+
+/// Set the secrets of the given entries, creating items as necessary.
+pub(crate) fn set_secrets(
+    ss: &Arc<Service<'static>>,
+    entries: &[(&str, &str, &[u8])],
+) -> Result<Vec<Result<()>>> {
+    let names: Vec<(&str, &str)> = entries.iter().map(|(s, u, _)| (*s, *u)).collect();
+    let found = find_unique_items(ss, &names)?;
+    let updates: Vec<(&OwnedObjectPath, &[u8])> = found
+        .iter()
+        .zip(entries.iter())
+        .filter_map(|(r, (_, _, secret))| r.as_ref().ok().map(|p| (p, *secret)))
+        .collect();
+    let mut updated = ss
+        .set_secrets_batch(&updates, DEFAULT_CONTENT_TYPE)
+        .into_iter();
+    let results = found
+        .into_iter()
+        .zip(entries.iter())
+        .map(|(r, (service, user, secret))| match r {
+            Ok(_) => updated.next().expect("one result per update"),
+            Err(Error::NoEntry) => Specifier::new(
+                ss.clone(),
+                None,
+                None,
+                false,
+                None,
+                None,
+                BTreeMap::new(),
+                Profile::Keyring,
+                service,
+                user,
+            )
+            .set_secret(secret),
+            Err(err) => Err(err),
+        })
+        .collect();
+    Ok(results)
+}
+
+/// Find the unique item for each of the given entries.
+fn find_unique_items(
+    ss: &Arc<Service<'static>>,
+    entries: &[(&str, &str)],
+) -> Result<Vec<Result<OwnedObjectPath>>> {
+    let specs: Vec<HashMap<&str, &str>> = entries
+        .iter()
+        .map(|(service, user)| HashMap::from([("service", *service), ("username", *user)]))
+        .collect();
+    let found = ss.find_matching_items_batch(&specs)?;
+    let results = found
+        .into_iter()
+        .map(|mut paths| match paths.len() {
+            0 => Err(Error::NoEntry),
+            1 => Ok(paths.remove(0)),
+            _ => Err(Error::Ambiguous(
+                paths
+                    .into_iter()
+                    .map(|p| Entry::new_with_credential(Wrapper::new(ss.clone(), p)))
+                    .collect(),
+            )),
+        })
+        .collect();
+    Ok(results)
+}
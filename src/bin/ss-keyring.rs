@@ -0,0 +1,396 @@
+//! Command-line tool for inspecting and managing Secret Service items
+//! with this crate's credential store (`-h` or `--help` for usage).
+//!
+//! This binary requires the `cli` feature, plus one of the crypto features:
+//! `cargo install zbus-secret-service-keyring-store --features cli,crypto-rust`
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::Arc;
+
+use clap::{Args, Parser, Subcommand};
+use serde_json::{Value, json};
+
+use keyring_core::api::CredentialStoreApi;
+use keyring_core::{Entry, Error, Result};
+use zbus_secret_service_keyring_store::Store;
+use zbus_secret_service_keyring_store::cred::{Specifier, Wrapper};
+
+#[derive(Parser)]
+#[command(version, about = "Inspect and manage Secret Service items")]
+struct Cli {
+    /// Produce JSON output (for scripting)
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List items, optionally filtered by attributes
+    #[command(visible_alias = "list")]
+    Search {
+        /// An attribute to match, as key=value (may be repeated)
+        #[arg(short, long = "attr", value_parser = parse_pair)]
+        attributes: Vec<(String, String)>,
+        /// Search only this collection
+        #[arg(short, long)]
+        collection: Option<String>,
+    },
+    /// Print the secret of an entry
+    Get {
+        #[command(flatten)]
+        entry: EntryArgs,
+        /// Print the secret in hex rather than as a UTF-8 string
+        #[arg(long)]
+        hex: bool,
+    },
+    /// Set the secret of an entry from standard input
+    Set {
+        #[command(flatten)]
+        entry: EntryArgs,
+        /// The label for a newly created item
+        #[arg(short, long)]
+        label: Option<String>,
+        /// Keep a trailing newline on the input, rather than removing it
+        #[arg(long)]
+        raw: bool,
+    },
+    /// Delete an entry
+    Delete {
+        #[command(flatten)]
+        entry: EntryArgs,
+    },
+    /// Show the label and attributes of an entry
+    Show {
+        #[command(flatten)]
+        entry: EntryArgs,
+    },
+    /// Manage collections
+    #[command(subcommand)]
+    Collection(CollectionCommand),
+}
+
+#[derive(Subcommand)]
+enum CollectionCommand {
+    /// List collections
+    List,
+    /// Create a collection
+    Create { name: String },
+    /// Delete a collection and all its items
+    Delete { name: String },
+    /// Lock a collection
+    Lock { name: String },
+    /// Unlock a collection
+    Unlock { name: String },
+}
+
+#[derive(Args)]
+struct EntryArgs {
+    /// The entry's service
+    #[arg(short, long)]
+    service: String,
+    /// The entry's user
+    #[arg(short, long)]
+    user: String,
+    /// The entry's target collection
+    #[arg(short, long)]
+    target: Option<String>,
+}
+
+fn parse_pair(s: &str) -> std::result::Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((k, v)) => Ok((k.to_string(), v.to_string())),
+        None => Err(format!("'{s}' is not of the form key=value")),
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let result = Store::new().and_then(|store| run(&store, &cli.command, cli.json));
+    if let Err(err) = result {
+        if cli.json {
+            println!("{}", json!({ "error": err.to_string() }));
+        } else {
+            eprintln!("Error: {err}");
+        }
+        std::process::exit(1);
+    }
+}
+
+fn run(store: &Arc<Store>, command: &Command, as_json: bool) -> Result<()> {
+    match command {
+        Command::Search {
+            attributes,
+            collection,
+        } => {
+            let spec: HashMap<&str, &str> = attributes
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect();
+            let entries = match collection {
+                Some(c) => store.search_collection(c, &spec)?,
+                None => store.search(&spec)?,
+            };
+            let mut items = Vec::with_capacity(entries.len());
+            for entry in entries {
+                items.push(describe_wrapper(&entry)?);
+            }
+            if as_json {
+                println!("{}", Value::Array(items));
+            } else {
+                for item in items {
+                    print!("{}", format_item(&item));
+                }
+            }
+        }
+        Command::Get { entry, hex } => {
+            let secret = build(store, entry, None)?.get_secret()?;
+            let text = if *hex {
+                secret.iter().map(|b| format!("{b:02x}")).collect()
+            } else {
+                String::from_utf8(secret).map_err(|e| Error::BadEncoding(e.into_bytes()))?
+            };
+            if as_json {
+                println!("{}", json!({ "secret": text }));
+            } else {
+                println!("{text}");
+            }
+        }
+        Command::Set { entry, label, raw } => {
+            let mut secret = Vec::new();
+            std::io::stdin()
+                .read_to_end(&mut secret)
+                .map_err(|e| Error::PlatformFailure(Box::new(e)))?;
+            if !raw && secret.last() == Some(&b'\n') {
+                secret.pop();
+            }
+            build(store, entry, label.as_deref())?.set_secret(&secret)?;
+            report(as_json, "set");
+        }
+        Command::Delete { entry } => {
+            build(store, entry, None)?.delete_credential()?;
+            report(as_json, "deleted");
+        }
+        Command::Show { entry } => {
+            let entry = build(store, entry, None)?;
+            let specifier = entry
+                .as_any()
+                .downcast_ref::<Specifier>()
+                .ok_or_else(|| unexpected_credential("a Secret Service item specifier"))?;
+            let label = specifier.get_label()?;
+            let attributes = entry.get_attributes()?;
+            if as_json {
+                println!("{}", json!({ "label": label, "attributes": attributes }));
+            } else {
+                println!("{label}");
+                print!("{}", format_attributes(&json!(attributes)));
+            }
+        }
+        Command::Collection(command) => run_collection(store, command, as_json)?,
+    }
+    Ok(())
+}
+
+fn run_collection(store: &Arc<Store>, command: &CollectionCommand, as_json: bool) -> Result<()> {
+    match command {
+        CollectionCommand::List => {
+            let collections = store.collections()?;
+            if as_json {
+                let list: Vec<Value> = collections
+                    .iter()
+                    .map(|c| {
+                        json!({
+                            "label": c.label,
+                            "path": c.path,
+                            "aliases": c.aliases,
+                            "locked": c.locked,
+                        })
+                    })
+                    .collect();
+                println!("{}", Value::Array(list));
+            } else {
+                for c in collections {
+                    let state = if c.locked { "locked" } else { "unlocked" };
+                    let aliases = if c.aliases.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" [{}]", c.aliases.join(", "))
+                    };
+                    println!("{}{aliases} ({}): {state}", c.label, c.path);
+                }
+            }
+        }
+        CollectionCommand::Create { name } => {
+            store.create_collection(name)?;
+            report(as_json, "created");
+        }
+        CollectionCommand::Delete { name } => {
+            store.delete_collection(name)?;
+            report(as_json, "deleted");
+        }
+        CollectionCommand::Lock { name } => {
+            store.lock_collection(name)?;
+            report(as_json, "locked");
+        }
+        CollectionCommand::Unlock { name } => {
+            store.unlock_collection(name)?;
+            report(as_json, "unlocked");
+        }
+    }
+    Ok(())
+}
+
+fn build(store: &Arc<Store>, args: &EntryArgs, label: Option<&str>) -> Result<Entry> {
+    let mut modifiers = HashMap::new();
+    if let Some(target) = &args.target {
+        modifiers.insert("target", target.as_str());
+    }
+    if let Some(label) = label {
+        modifiers.insert("label", label);
+    }
+    store.build(&args.service, &args.user, Some(&modifiers))
+}
+
+fn describe_wrapper(entry: &Entry) -> Result<Value> {
+    let wrapper = entry
+        .as_any()
+        .downcast_ref::<Wrapper>()
+        .ok_or_else(|| unexpected_credential("a Secret Service item"))?;
+    Ok(json!({
+        "path": wrapper.get_path(),
+        "label": wrapper.get_label()?,
+        "attributes": entry.get_attributes()?,
+    }))
+}
+
+fn unexpected_credential(expected: &str) -> Error {
+    Error::NotSupportedByStore(format!(
+        "The store returned an entry that isn't for {expected}"
+    ))
+}
+
+/// Format an item (as described by [describe_wrapper]) for plain output.
+fn format_item(item: &Value) -> String {
+    format!(
+        "{} ({})\n{}",
+        item["label"].as_str().unwrap_or_default(),
+        item["path"].as_str().unwrap_or_default(),
+        format_attributes(&item["attributes"])
+    )
+}
+
+/// Format attributes for plain output, one per line, sorted by name.
+fn format_attributes(attributes: &Value) -> String {
+    let mut result = String::new();
+    if let Some(map) = attributes.as_object() {
+        let mut keys: Vec<&String> = map.keys().collect();
+        keys.sort();
+        for k in keys {
+            result.push_str(&format!(
+                "    {k} = {}\n",
+                map[k].as_str().unwrap_or_default()
+            ));
+        }
+    }
+    result
+}
+
+fn report(as_json: bool, what: &str) {
+    if as_json {
+        println!("{}", json!({ "result": what }));
+    } else {
+        println!("{what}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_command_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_argument_parsing() {
+        let cli = Cli::try_parse_from([
+            "ss-keyring",
+            "--json",
+            "list",
+            "-a",
+            "service=my app",
+            "--attr",
+            "x=a=b",
+        ])
+        .unwrap();
+        assert!(cli.json);
+        let Command::Search {
+            attributes,
+            collection,
+        } = cli.command
+        else {
+            panic!("list is not a search");
+        };
+        assert_eq!(
+            attributes,
+            vec![
+                ("service".to_string(), "my app".to_string()),
+                ("x".to_string(), "a=b".to_string())
+            ]
+        );
+        assert_eq!(collection, None);
+        let cli = Cli::try_parse_from([
+            "ss-keyring",
+            "get",
+            "-s",
+            "svc",
+            "-u",
+            "alice",
+            "-t",
+            "alias:session",
+            "--hex",
+        ])
+        .unwrap();
+        let Command::Get { entry, hex } = cli.command else {
+            panic!("get is not a get");
+        };
+        assert!(hex);
+        assert_eq!(
+            (entry.service.as_str(), entry.user.as_str()),
+            ("svc", "alice")
+        );
+        assert_eq!(entry.target.as_deref(), Some("alias:session"));
+        let cli = Cli::try_parse_from(["ss-keyring", "collection", "create", "work"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Collection(CollectionCommand::Create { name }) if name == "work"
+        ));
+        for bad in [
+            vec!["ss-keyring", "search", "-a", "no-equals-sign"],
+            vec!["ss-keyring", "get", "-s", "svc"],
+            vec!["ss-keyring", "frobnicate"],
+        ] {
+            assert!(Cli::try_parse_from(bad).is_err());
+        }
+    }
+
+    #[test]
+    fn test_plain_output() {
+        let item = json!({
+            "path": "/org/freedesktop/secrets/collection/login/1",
+            "label": "keyring:alice@my app",
+            "attributes": { "username": "alice", "service": "my app" },
+        });
+        assert_eq!(
+            format_item(&item),
+            "keyring:alice@my app (/org/freedesktop/secrets/collection/login/1)\n\
+             \x20   service = my app\n\
+             \x20   username = alice\n"
+        );
+        assert_eq!(format_attributes(&json!({})), "");
+    }
+}
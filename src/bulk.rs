@@ -0,0 +1,198 @@
+/*!
+
+Bulk deletion of items.
+
+[Store::delete_matching](crate::Store::delete_matching) deletes the items found
+by a search spec (attribute equality, as for
+[search](keyring_core::api::CredentialStoreApi::search)) that also pass a
+[DeleteFilter]. The filter can require item labels and attribute values to
+match glob patterns, in which `*` matches any sequence of characters and `?`
+matches any single character, and can supply a predicate for any other test.
+
+Locked items are never unlocked, so bulk deletion never prompts the user:
+matching items that are locked are reported as skipped. In a dry run, the
+report lists the items that would be deleted, and nothing is deleted.
+
+Deleting an item also deletes the previous versions of its secret kept by
+its [history](crate::history). History items aren't listed in the report.
+
+*/
+
+use std::collections::{HashMap, HashSet};
+
+use keyring_core::{Error, Result};
+use zbus::zvariant::OwnedObjectPath;
+
+use crate::history;
+use crate::service::Service;
+
+/// A test applied to each item found by a bulk deletion.
+pub type Predicate = Box<dyn Fn(&MatchedItem) -> bool>;
+
+/// Which of the items found by a search are to be deleted.
+///
+/// The default filter passes every item found.
+#[derive(Default)]
+pub struct DeleteFilter {
+    /// A glob that each item's label must match.
+    pub label: Option<String>,
+    /// Globs that each item's attribute values must match, by attribute name.
+    /// An item without one of these attributes doesn't match.
+    pub attributes: HashMap<String, String>,
+    /// A test that each item must also pass.
+    pub predicate: Option<Predicate>,
+    /// Report what would be deleted, but don't delete anything.
+    pub dry_run: bool,
+}
+
+impl std::fmt::Debug for DeleteFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeleteFilter")
+            .field("label", &self.label)
+            .field("attributes", &self.attributes)
+            .field("predicate", &self.predicate.as_ref().map(|_| "<fn>"))
+            .field("dry_run", &self.dry_run)
+            .finish()
+    }
+}
+
+impl DeleteFilter {
+    fn matches(&self, item: &MatchedItem) -> bool {
+        if let Some(pattern) = &self.label
+            && !glob_match(pattern, &item.label)
+        {
+            return false;
+        }
+        for (key, pattern) in self.attributes.iter() {
+            match item.attributes.get(key) {
+                Some(value) if glob_match(pattern, value) => {}
+                _ => return false,
+            }
+        }
+        self.predicate.as_ref().map(|p| p(item)).unwrap_or(true)
+    }
+}
+
+/// An item that matched a bulk deletion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchedItem {
+    /// The item's D-Bus object path.
+    pub path: String,
+    pub label: String,
+    pub attributes: HashMap<String, String>,
+}
+
+/// The outcome of a bulk deletion.
+#[derive(Debug, Default)]
+pub struct DeletionReport {
+    /// Items deleted (or, in a dry run, that would have been deleted).
+    pub deleted: Vec<MatchedItem>,
+    /// Matching items left alone because they are locked.
+    pub skipped_locked: Vec<MatchedItem>,
+    /// Items that couldn't be examined or deleted, with the error that occurred.
+    pub failed: Vec<(MatchedItem, Error)>,
+}
+
+impl std::fmt::Display for DeletionReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for item in &self.deleted {
+            writeln!(f, "deleted {} ({})", item.label, item.path)?;
+        }
+        for item in &self.skipped_locked {
+            writeln!(f, "skipped locked {} ({})", item.label, item.path)?;
+        }
+        for (item, err) in &self.failed {
+            writeln!(f, "failed {} ({}): {err}", item.label, item.path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Delete the items matching both the spec and the filter.
+pub(crate) fn delete_matching(
+    ss: &Service,
+    spec: &HashMap<&str, &str>,
+    filter: &DeleteFilter,
+) -> Result<DeletionReport> {
+    let (unlocked, locked) = ss.find_matching_items_by_lock(spec)?;
+    let mut report = DeletionReport::default();
+    // history items already deleted along with the items they belong to
+    let mut deleted_history = HashSet::new();
+    for (path, is_locked) in unlocked
+        .into_iter()
+        .map(|p| (p, false))
+        .chain(locked.into_iter().map(|p| (p, true)))
+    {
+        if deleted_history.contains(&path) {
+            continue;
+        }
+        let item = match describe(ss, &path) {
+            Ok(item) => item,
+            Err(err) => {
+                let item = MatchedItem {
+                    path: path.to_string(),
+                    label: String::new(),
+                    attributes: HashMap::new(),
+                };
+                report.failed.push((item, err));
+                continue;
+            }
+        };
+        if !filter.matches(&item) {
+            continue;
+        }
+        if is_locked {
+            report.skipped_locked.push(item);
+        } else if filter.dry_run {
+            report.deleted.push(item);
+        } else {
+            let result = {
+                let ex = ss.exclusive();
+                history::delete_versions(&ex, &item.attributes).and_then(|paths| {
+                    deleted_history.extend(paths);
+                    ex.delete(&path)
+                })
+            };
+            match result {
+                Ok(()) => report.deleted.push(item),
+                Err(err) => report.failed.push((item, err)),
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn describe(ss: &Service, path: &OwnedObjectPath) -> Result<MatchedItem> {
+    Ok(MatchedItem {
+        path: path.to_string(),
+        label: ss.get_label(path)?,
+        attributes: ss.get_attributes(path)?,
+    })
+}
+
+/// Match a value against a glob in which `*` matches any sequence
+/// of characters and `?` matches any single character.
+fn glob_match(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let value: Vec<char> = value.chars().collect();
+    let (mut p, mut v) = (0, 0);
+    // where to resume after the most recent `*`: (pattern index, value index)
+    let mut backtrack: Option<(usize, usize)> = None;
+    while v < value.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == value[v]) {
+            p += 1;
+            v += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, v));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // let the `*` absorb one more character
+            p = star + 1;
+            v = matched + 1;
+            backtrack = Some((star, v));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
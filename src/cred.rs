@@ -1,5 +1,6 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use zbus::zvariant::OwnedObjectPath;
 
@@ -7,17 +8,60 @@ use keyring_core::Entry;
 use keyring_core::api::{Credential, CredentialApi};
 use keyring_core::error::{Error, Result};
 
-use crate::service::Service;
+use crate::diagnostics::CollectionStatus;
+use crate::errors::{conflict, expired};
+use crate::handle;
+use crate::history::{self, HISTORY_ID_ATTRIBUTE, SecretVersion};
+use crate::profile::{Profile, SCHEMA_ATTRIBUTE};
+use crate::service::{ALIAS_PREFIX, Service, collection_path_of};
+use crate::uri::{self, CredentialUri};
+
+/// The attribute that records when an item expires, in seconds since the Unix epoch.
+pub const EXPIRES_ATTRIBUTE: &str = "keyring:expires";
+
+/// The attribute that records the version of the field layout
+/// of a [structured](crate::structured) credential.
+pub const FIELDS_VERSION_ATTRIBUTE: &str = "keyring:schema-version";
+
+/// The prefix on a modifier name that declares an extra identifying attribute.
+pub const ATTRIBUTE_PREFIX: &str = "attribute:";
+
+/// The attribute that names an item's extra identifying attributes (other than
+/// those of its profile's schema), as a comma-separated list of percent-encoded
+/// names, so that they can be recovered from the item.
+pub const IDENTIFYING_ATTRIBUTE: &str = "keyring:identifying";
+
+/// The attributes controlled by this crate, which clients cannot update.
+pub(crate) const RESERVED_ATTRIBUTES: [&str; 7] = [
+    "service",
+    "username",
+    "target",
+    EXPIRES_ATTRIBUTE,
+    HISTORY_ID_ATTRIBUTE,
+    FIELDS_VERSION_ATTRIBUTE,
+    IDENTIFYING_ATTRIBUTE,
+];
 
 /// The specifier for an item in the secret-service.
 ///
-/// The label and target are captured from the modifiers
+/// The label, target, ephemeral flag, expiry, history retention,
+/// and extra identifying attributes are captured from the modifiers
 /// used when the specifier was created, so they are
 /// available when a matching item needs to be created.
 pub struct Specifier {
     ss: Arc<Service<'static>>,
     pub label: String,
     pub target: Option<String>,
+    pub ephemeral: bool,
+    /// When items set through this specifier expire, in seconds since the Unix epoch.
+    pub expires: Option<u64>,
+    /// How many previous versions of the secret to keep (see the [history] module).
+    pub history: Option<usize>,
+    /// Identifying attributes, beyond the service, user, and target,
+    /// that are searched for and set on new items.
+    pub attributes: BTreeMap<String, String>,
+    /// The layout of the specifier's items (see the [profile](crate::profile) module).
+    pub profile: Profile,
     pub service: String,
     pub user: String,
 }
@@ -27,29 +71,77 @@ impl std::fmt::Debug for Specifier {
         f.debug_struct("Specifier")
             .field("label", &self.label)
             .field("target", &self.target)
+            .field("ephemeral", &self.ephemeral)
+            .field("expires", &self.expires)
+            .field("history", &self.history)
+            .field("attributes", &self.attributes)
+            .field("profile", &self.profile)
             .field("service", &self.service)
             .field("user", &self.user)
             .finish()
     }
 }
 
+impl std::fmt::Display for Specifier {
+    /// Formats the specifier as a [credential URI](crate::uri).
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut modifiers = BTreeMap::new();
+        if self.label != self.default_label(&self.service, &self.user) {
+            modifiers.insert("label".to_string(), self.label.clone());
+        }
+        if self.profile != Profile::Keyring {
+            modifiers.insert("profile".to_string(), self.profile.name().to_string());
+        }
+        if self.ephemeral {
+            modifiers.insert("ephemeral".to_string(), "true".to_string());
+        }
+        if let Some(expires) = self.expires {
+            modifiers.insert("expires".to_string(), expires.to_string());
+        }
+        if let Some(history) = self.history {
+            modifiers.insert("history".to_string(), history.to_string());
+        }
+        for (name, value) in self.attributes.iter() {
+            modifiers.insert(format!("{ATTRIBUTE_PREFIX}{name}"), value.clone());
+        }
+        let uri = CredentialUri {
+            target: self.target.clone(),
+            service: self.service.clone(),
+            user: self.user.clone(),
+            modifiers,
+        };
+        std::fmt::Display::fmt(&uri, f)
+    }
+}
+
 impl Specifier {
     /// Create a specifier.
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         ss: Arc<Service<'static>>,
         label: Option<&str>,
         target: Option<&str>,
+        ephemeral: bool,
+        expires: Option<u64>,
+        history: Option<usize>,
+        attributes: BTreeMap<String, String>,
+        profile: Profile,
         service: &str,
         user: &str,
     ) -> Arc<Self> {
         let label = match label {
-            None => format!("keyring:{user}@{service}"),
+            None => profile.default_label(service, user, &attributes),
             Some(l) => l.to_string(),
         };
         Arc::new(Self {
             ss,
             label,
             target: target.map(|s| s.to_string()),
+            ephemeral,
+            expires,
+            history,
+            attributes,
+            profile,
             service: service.to_string(),
             user: user.to_string(),
         })
@@ -80,6 +172,164 @@ impl Specifier {
         }
     }
 
+    /// Moves an existing, matching item into the named target collection,
+    /// creating the collection if necessary.
+    ///
+    /// Returns an entry for a specifier like this one but with the new target
+    /// (and not ephemeral), which finds the moved item. See
+    /// [move_to_collection](Wrapper::move_to_collection) for how the item is moved.
+    pub fn retarget(&self, new_target: &str) -> Result<Entry> {
+        if new_target.is_empty() || new_target == ALIAS_PREFIX {
+            return Err(Error::Invalid(
+                "target".to_string(),
+                "must not be empty".to_string(),
+            ));
+        }
+        let path = self.get_unique_item()?;
+        self.ss.move_item(&path, new_target)?;
+        Ok(Entry::new_with_credential(Specifier::new(
+            self.ss.clone(),
+            Some(&self.label),
+            Some(new_target),
+            false,
+            self.expires,
+            self.history,
+            self.attributes.clone(),
+            self.profile,
+            &self.service,
+            &self.user,
+        )))
+    }
+
+    /// Renames an existing, matching item by changing its service and user.
+    ///
+    /// Returns an entry for a specifier like this one but with the new service
+    /// and user, which finds the renamed item. It's an error (and nothing is
+    /// changed) if an item for the new service and user already exists, since
+    /// renaming would then make the new entry ambiguous. The check and the
+    /// rename are made without any other operation through this store intervening.
+    ///
+    /// If `relabel` is true, and the item has the default label for this
+    /// specifier's service and user, it's given the default label for the new
+    /// ones. Labels that aren't in the default format are never changed. Whether
+    /// or not `relabel` is true, the returned entry uses the default label for
+    /// new items only if this specifier does.
+    pub fn rename(&self, new_service: &str, new_user: &str, relabel: bool) -> Result<Entry> {
+        let old_label = self.default_label(&self.service, &self.user);
+        let label = if self.label == old_label {
+            None
+        } else {
+            Some(self.label.as_str())
+        };
+        let renamed = Specifier::new(
+            self.ss.clone(),
+            label,
+            self.target.as_deref(),
+            self.ephemeral,
+            self.expires,
+            self.history,
+            self.attributes.clone(),
+            self.profile,
+            new_service,
+            new_user,
+        );
+        let path = self.get_unique_item()?;
+        if new_service == self.service && new_user == self.user {
+            return Ok(Entry::new_with_credential(renamed));
+        }
+        let ex = self.ss.exclusive();
+        if !ex
+            .find_matching_items(&renamed.search_attributes())?
+            .is_empty()
+        {
+            return Err(Error::Invalid(
+                "specifier".to_string(),
+                format!("an item for {new_user}@{new_service} already exists"),
+            ));
+        }
+        let attributes = HashMap::from([
+            (self.profile.service_attribute(), new_service),
+            (self.profile.user_attribute(), new_user),
+        ]);
+        ex.update_attributes(&path, &attributes)?;
+        if relabel && ex.get_label(&path)? == old_label {
+            ex.set_label(&path, &self.default_label(new_service, new_user))?;
+        }
+        Ok(Entry::new_with_credential(renamed))
+    }
+
+    /// Lists the previous versions kept for an existing, matching item, newest first.
+    pub fn list_versions(&self) -> Result<Vec<SecretVersion>> {
+        let path = self.get_unique_item()?;
+        history::list_versions(&self.ss.exclusive(), &path)
+    }
+
+    /// Returns a previous version of the secret of an existing, matching item.
+    pub fn get_version(&self, version: u32) -> Result<Vec<u8>> {
+        let path = self.get_unique_item()?;
+        history::get_version(&self.ss.exclusive(), &path, version)
+    }
+
+    /// Restores a previous version of the secret of an existing, matching item.
+    ///
+    /// If the specifier keeps history, the secret being replaced is archived
+    /// as a new version, so the rollback can itself be undone.
+    pub fn rollback(&self, version: u32) -> Result<()> {
+        let secret = self.get_version(version)?;
+        self.set_secret(&secret)
+    }
+
+    /// Sets the secret of an existing, matching item, but only if its current
+    /// secret is the expected one.
+    ///
+    /// Returns a [conflict](crate::errors::conflict) error if the current secret
+    /// is not the expected one. Use this to keep concurrent rotations of
+    /// the same credential from overwriting each other's updates. As with
+    /// [set_secret](CredentialApi::set_secret), the item's expiry (if the
+    /// specifier has one) is updated and, once the comparison has succeeded,
+    /// the replaced secret is archived (if the specifier keeps history).
+    /// Expired items can be updated.
+    ///
+    /// The comparison and update are atomic with respect to all other operations
+    /// through the same store. They can't be atomic with respect to other
+    /// Secret Service clients, because the Secret Service has no conditional
+    /// update: there is a window (between the final check of the item's
+    /// `Modified` property and the write, usually well under a millisecond) in
+    /// which another client's update would be overwritten. Also, since `Modified`
+    /// only has a resolution of one second, an update by another client that
+    /// restores the expected secret within the same second is not detected.
+    /// (The tests check that an update through another store is detected once
+    /// made, but not concurrent updates through separate stores.)
+    pub fn compare_and_set(&self, expected: &[u8], secret: &[u8]) -> Result<()> {
+        let path = self.get_unique_item()?;
+        if !self.ss.compare_and_set_secret(
+            &path,
+            expected,
+            secret,
+            self.profile.content_type(),
+            self.history,
+        )? {
+            return Err(conflict());
+        }
+        if let Some(expires) = self.expires {
+            let expires = expires.to_string();
+            let attributes = HashMap::from([(EXPIRES_ATTRIBUTE, expires.as_str())]);
+            self.ss.update_attributes(&path, &attributes)?;
+        }
+        Ok(())
+    }
+
+    /// The collection in which a new item for this specifier is created.
+    ///
+    /// Ephemeral specifiers always use the in-memory session collection.
+    fn collection(&self) -> String {
+        if self.ephemeral {
+            "alias:session".to_string()
+        } else {
+            self.target.clone().unwrap_or("default".to_string())
+        }
+    }
+
     fn get_unique_item(&self) -> Result<OwnedObjectPath> {
         let paths = self.ss.find_matching_items(&self.search_attributes())?;
         match paths.len() {
@@ -98,37 +348,113 @@ impl Specifier {
         }
     }
 
+    /// Set the secret (with the given content type) of the matching item,
+    /// and add the given attributes (and the specifier's expiry, if any)
+    /// to it, creating the item if necessary.
+    pub(crate) fn set_typed_secret(
+        &self,
+        secret: &[u8],
+        content_type: &str,
+        attributes: &HashMap<&str, &str>,
+    ) -> Result<()> {
+        let mut attributes = attributes.clone();
+        let expires = self.expires.map(|t| t.to_string());
+        if let Some(expires) = &expires {
+            attributes.insert(EXPIRES_ATTRIBUTE, expires.as_str());
+        }
+        // first try to find a unique, existing, matching item and set its password
+        match self.get_unique_item() {
+            Ok(p) => {
+                if let Some(keep) = self.history {
+                    history::archive(&self.ss.exclusive(), &p, secret, keep)?;
+                }
+                self.ss.set_secret(&p, secret, content_type)?;
+                if !attributes.is_empty() {
+                    self.ss.update_attributes(&p, &attributes)?;
+                }
+                return Ok(());
+            }
+            Err(Error::NoEntry) => {}
+            Err(err) => return Err(err),
+        }
+        // if there is no existing item, create one for this credential.
+        let identifying = self.identifying_names();
+        let mut all_attributes = attributes.clone();
+        all_attributes.extend(self.search_attributes());
+        if let Some(identifying) = &identifying {
+            all_attributes.insert(IDENTIFYING_ATTRIBUTE, identifying.as_str());
+        }
+        self.ss
+            .create_item(
+                &self.collection(),
+                &self.label,
+                all_attributes,
+                secret,
+                content_type,
+                true,
+            )
+            .map(|_| ())
+    }
+
+    /// Get the secret and the attributes of the matching item.
+    ///
+    /// Returns an [expired](crate::errors::expired) error if the item has expired.
+    pub(crate) fn get_secret_and_attributes(&self) -> Result<(Vec<u8>, HashMap<String, String>)> {
+        let path = self.get_unique_item()?;
+        let attributes = self.ss.get_attributes(&path)?;
+        check_expiry(&attributes)?;
+        let secret = self.ss.get_secret(&path)?;
+        Ok((secret, attributes))
+    }
+
+    /// The label this specifier would give new items for the given service and user
+    /// if it had no `label` modifier.
+    fn default_label(&self, service: &str, user: &str) -> String {
+        self.profile.default_label(service, user, &self.attributes)
+    }
+
+    /// The [IDENTIFYING_ATTRIBUTE] value for new items: the names outside the schema.
+    fn identifying_names(&self) -> Option<String> {
+        let names: Vec<String> = self
+            .attributes
+            .keys()
+            .filter(|k| !self.profile.schema_attributes().contains(&k.as_str()))
+            .map(|k| uri::encode(k))
+            .collect();
+        (!names.is_empty()).then(|| names.join(","))
+    }
+
     /// Provide a HashMap of search attributes for this specifier.
     fn search_attributes(&self) -> HashMap<&str, &str> {
         let mut result: HashMap<&str, &str> = HashMap::new();
-        result.insert("service", self.service.as_str());
-        result.insert("username", self.user.as_str());
+        result.insert(self.profile.service_attribute(), self.service.as_str());
+        result.insert(self.profile.user_attribute(), self.user.as_str());
+        if let Some(schema) = self.profile.schema() {
+            result.insert(SCHEMA_ATTRIBUTE, schema);
+        }
         if let Some(target) = &self.target {
             result.insert("target", target.as_str());
         }
+        for (name, value) in self.attributes.iter() {
+            result.insert(name.as_str(), value.as_str());
+        }
         result
     }
 }
 
 impl CredentialApi for Specifier {
     /// See the keyring-core API docs.
+    ///
+    /// If the specifier has an expiry, it is set on the item.
     fn set_secret(&self, secret: &[u8]) -> Result<()> {
-        // first try to find a unique, existing, matching item and set its password
-        match self.get_unique_item() {
-            Ok(p) => return self.ss.set_secret(&p, secret),
-            Err(Error::NoEntry) => {}
-            Err(err) => return Err(err),
-        }
-        // if there is no existing item, create one for this credential.
-        let collection = self.target.clone().unwrap_or("default".to_string());
-        self.ss
-            .create_item(&collection, &self.label, self.search_attributes(), secret)
+        self.set_typed_secret(secret, self.profile.content_type(), &HashMap::new())
     }
 
     /// See the keyring-core API docs.
+    ///
+    /// Returns an [expired](crate::errors::expired) error if the item has expired.
     fn get_secret(&self) -> Result<Vec<u8>> {
-        let path = self.get_unique_item()?;
-        self.ss.get_secret(&path)
+        self.get_secret_and_attributes().map(|(secret, _)| secret)
     }
 
     /// See the keyring-core API docs.
@@ -138,9 +464,14 @@ impl CredentialApi for Specifier {
     }
 
     /// See the keyring-core API docs.
+    ///
+    /// The specifier's identifying attributes can't be updated.
     fn update_attributes(&self, attributes: &HashMap<&str, &str>) -> Result<()> {
         for key in attributes.keys() {
-            if *key == "target" || *key == "service" || *key == "username" {
+            if RESERVED_ATTRIBUTES.contains(key)
+                || self.attributes.contains_key(*key)
+                || self.profile.controls(key)
+            {
                 return Err(Error::Invalid(
                     key.to_string(),
                     "cannot be updated".to_string(),
@@ -152,8 +483,11 @@ impl CredentialApi for Specifier {
     }
 
     /// See the keyring-core API docs.
+    ///
+    /// Any previous versions kept for the item are also deleted.
     fn delete_credential(&self) -> Result<()> {
         let path = self.get_unique_item()?;
+        history::delete_history(&self.ss.exclusive(), &path)?;
         self.ss.delete(&path)
     }
 
@@ -191,7 +525,21 @@ pub struct Wrapper {
 
 impl std::fmt::Debug for Wrapper {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Wrapper").field("path", &self.path).finish()
+        let mut d = f.debug_struct("Wrapper");
+        d.field("path", &self.path.as_str());
+        if let Ok(collection) = collection_path_of(&self.path) {
+            d.field("collection", &collection.as_str());
+        }
+        d.finish()
+    }
+}
+
+impl std::fmt::Display for Wrapper {
+    /// Formats the wrapper as its item's path. (Formatting a wrapper never
+    /// calls the Secret Service: use [uri](Wrapper::uri) to get the
+    /// [credential URI](crate::uri) that finds its item.)
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.path.as_str())
     }
 }
 
@@ -216,18 +564,134 @@ impl Wrapper {
     pub fn get_path(&self) -> String {
         self.path.to_string()
     }
+
+    /// Returns the [credential URI](crate::uri) of the specifier that
+    /// [to_specifier](Wrapper::to_specifier) would build. The item is not unlocked.
+    pub fn uri(&self) -> Result<String> {
+        Ok(self.specifier()?.to_string())
+    }
+
+    /// Builds an entry with a specifier that matches the wrapped item.
+    ///
+    /// The specifier's [profile](crate::profile) is the one whose schema is in
+    /// the item's `xdg:schema` attribute (or `keyring` if there is none). Its
+    /// service, user, target, and label are taken from the item's attributes
+    /// (`service`, `username`, and `target` for the `keyring` profile) and its
+    /// label. Its extra identifying attributes are the other attributes of its
+    /// profile's schema that the item has, plus those named by the item's
+    /// `keyring:identifying` attribute (which records the `attribute:` modifiers
+    /// it was created with). If the item has a `keyring:expires` attribute, the
+    /// specifier has the same expiry, and an item in the session collection
+    /// with no `target` attribute gets an ephemeral specifier. (The number of
+    /// previous versions to keep isn't recorded on the item, so the specifier
+    /// doesn't keep history.)
+    ///
+    /// Returns an [Invalid](Error::Invalid) error, naming the missing attribute,
+    /// if the item has no service or user attribute: for example,
+    /// if it was created by some other application.
+    pub fn to_specifier(&self) -> Result<Entry> {
+        self.ss.ensure_unlocked(&self.path)?;
+        Ok(Entry::new_with_credential(self.specifier()?))
+    }
+
+    /// Build a specifier that matches the wrapped item, without unlocking it.
+    fn specifier(&self) -> Result<Arc<Specifier>> {
+        let attributes = self.ss.get_attributes(&self.path)?;
+        let required = |key: &str| {
+            attributes.get(key).ok_or_else(|| {
+                Error::Invalid(
+                    key.to_string(),
+                    "is missing: the item's layout is not one this store knows".to_string(),
+                )
+            })
+        };
+        let (profile, identifying) = identity_of(&attributes);
+        let service = required(profile.service_attribute())?;
+        let user = required(profile.user_attribute())?;
+        let target = attributes.get("target").map(String::as_str);
+        let ephemeral = target.is_none()
+            && self
+                .get_collection()?
+                .aliases
+                .iter()
+                .any(|a| a == "session");
+        let label = self.ss.get_label(&self.path)?;
+        Ok(Specifier::new(
+            self.ss.clone(),
+            Some(&label),
+            target,
+            ephemeral,
+            expiry_of(&attributes),
+            None,
+            identifying,
+            profile,
+            service,
+            user,
+        ))
+    }
+
+    /// Returns a handle that can be used to get an entry for the wrapped item
+    /// later, even in another run, without searching.
+    ///
+    /// See the [handle](crate::handle) module for the format of handles.
+    pub fn handle(&self) -> Result<String> {
+        Ok(handle::format(&self.path, self.fingerprint()?))
+    }
+
+    /// The fingerprint of the wrapped item, used to check handles.
+    pub(crate) fn fingerprint(&self) -> Result<u64> {
+        let created = self.ss.get_created(&self.path)?;
+        let attributes = self.ss.get_attributes(&self.path)?;
+        Ok(handle::fingerprint(created, &attributes))
+    }
+
+    /// Describes the collection that the wrapped item is in.
+    ///
+    /// This doesn't unlock the item or the collection, so it can be used to
+    /// tell apart the items in an [Ambiguous](Error::Ambiguous) error without
+    /// prompting the user. (The wrapper's `Debug` output only gives
+    /// the collection's path.)
+    pub fn get_collection(&self) -> Result<CollectionStatus> {
+        self.ss.get_collection_status(&self.path)
+    }
+
+    /// Moves the wrapped item into the named collection, creating the collection
+    /// if necessary, and returns an entry that wraps the moved item.
+    ///
+    /// The collection is named as a target is (see the crate docs), and the
+    /// moved item's `target` attribute is set to that name. Its label, other
+    /// attributes, secret, and content type are copied into the collection, the copy
+    /// is checked, and only then is the original deleted. It's an error if the
+    /// collection already has an item with the same attributes. Previous versions
+    /// kept for the item (see the [history] module) stay where they are, but
+    /// remain associated with the moved item.
+    pub fn move_to_collection(&self, collection: &str) -> Result<Entry> {
+        let path = self.ss.move_item(&self.path, collection)?;
+        Ok(Entry::new_with_credential(Wrapper::new(
+            self.ss.clone(),
+            path,
+        )))
+    }
 }
 
 impl CredentialApi for Wrapper {
     /// See the keyring-core API docs.
+    ///
+    /// The item keeps the content type it already has, so items laid out
+    /// by other applications (such as keytar's `text/plain`) stay readable to them.
     fn set_secret(&self, secret: &[u8]) -> Result<()> {
-        self.ss.ensure_unlocked(&self.path)?;
-        self.ss.set_secret(&self.path, secret)
+        let ex = self.ss.exclusive();
+        ex.ensure_unlocked(&self.path)?;
+        let content_type = ex.get_content_type(&self.path)?;
+        ex.set_secret(&self.path, secret, &content_type)
     }
 
     /// See the keyring-core API docs.
+    ///
+    /// Returns an [expired](crate::errors::expired) error if the item has expired.
     fn get_secret(&self) -> Result<Vec<u8>> {
         self.ss.ensure_unlocked(&self.path)?;
+        check_expiry(&self.ss.get_attributes(&self.path)?)?;
         self.ss.get_secret(&self.path)
     }
 
@@ -244,8 +708,11 @@ impl CredentialApi for Wrapper {
     }
 
     /// See the keyring-core API docs.
+    ///
+    /// Any previous versions kept for the item are also deleted.
     fn delete_credential(&self) -> Result<()> {
         self.ss.ensure_unlocked(&self.path)?;
+        history::delete_history(&self.ss.exclusive(), &self.path)?;
         self.ss.delete(&self.path)
     }
 
@@ -261,8 +728,9 @@ impl CredentialApi for Wrapper {
             return None;
         }
         let attributes = self.ss.get_attributes(&self.path).unwrap_or_default();
-        if let Some(service) = attributes.get("service")
-            && let Some(user) = attributes.get("username")
+        let profile = Profile::of_item(&attributes);
+        if let Some(service) = attributes.get(profile.service_attribute())
+            && let Some(user) = attributes.get(profile.user_attribute())
         {
             return Some((service.to_string(), user.to_string()));
         }
@@ -279,3 +747,51 @@ impl CredentialApi for Wrapper {
         std::fmt::Debug::fmt(self, f)
     }
 }
+
+/// The current time, in seconds since the Unix epoch.
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// When an item with the given attributes expires, if it does.
+///
+/// An expiry attribute whose value isn't a timestamp is ignored:
+/// this crate never writes one, so it's not ours to enforce.
+pub(crate) fn expiry_of(attributes: &HashMap<String, String>) -> Option<u64> {
+    attributes
+        .get(EXPIRES_ATTRIBUTE)
+        .and_then(|t| t.parse().ok())
+}
+
+/// The profile of an item with the given attributes, and its identifying
+/// attributes other than its service, user, and target: those of its profile's
+/// schema that it has, and those named by its [IDENTIFYING_ATTRIBUTE].
+pub(crate) fn identity_of(
+    attributes: &HashMap<String, String>,
+) -> (Profile, BTreeMap<String, String>) {
+    let profile = Profile::of_item(attributes);
+    let recorded: Vec<String> = attributes
+        .get(IDENTIFYING_ATTRIBUTE)
+        .map(|names| names.split(',').filter_map(uri::decode).collect())
+        .unwrap_or_default();
+    let identifying = profile
+        .schema_attributes()
+        .iter()
+        .map(|k| k.to_string())
+        .chain(recorded)
+        .filter(|k| !RESERVED_ATTRIBUTES.contains(&k.as_str()) && !profile.controls(k))
+        .filter_map(|k| attributes.get(&k).cloned().map(|v| (k, v)))
+        .collect();
+    (profile, identifying)
+}
+
+/// Fail if an item with the given attributes has expired.
+pub(crate) fn check_expiry(attributes: &HashMap<String, String>) -> Result<()> {
+    match expiry_of(attributes) {
+        Some(t) if t <= now() => Err(expired(t)),
+        _ => Ok(()),
+    }
+}
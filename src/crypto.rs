@@ -0,0 +1,103 @@
+/*!
+
+Passphrase-based encryption of local files.
+
+Data is sealed with XChaCha20-Poly1305 under a key derived from a
+passphrase with Argon2id. A sealed blob has the layout:
+
+```text
+magic (8 bytes) | salt (16 bytes) | nonce (24 bytes) | ciphertext
+```
+
+The magic identifies both the kind of file and its format version.
+Key derivation is deliberately slow, so callers that seal repeatedly
+should derive a key once per salt and reuse it.
+
+*/
+
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+
+use keyring_core::{Error, Result};
+
+pub(crate) const MAGIC_LEN: usize = 8;
+pub(crate) const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const HEADER_LEN: usize = MAGIC_LEN + SALT_LEN + NONCE_LEN;
+
+pub(crate) type Salt = [u8; SALT_LEN];
+
+/// A key derived from a passphrase and a salt.
+pub(crate) struct SealingKey {
+    pub(crate) salt: Salt,
+    key: Key,
+}
+
+impl SealingKey {
+    /// Derive a key from a passphrase with a fresh random salt.
+    pub(crate) fn new(passphrase: &str) -> Result<Self> {
+        let mut salt = [0u8; SALT_LEN];
+        fill_random(&mut salt)?;
+        Self::with_salt(passphrase, salt)
+    }
+
+    /// Derive a key from a passphrase and a known salt.
+    pub(crate) fn with_salt(passphrase: &str, salt: Salt) -> Result<Self> {
+        let mut key = Key::default();
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| Error::PlatformFailure(e.to_string().into()))?;
+        Ok(Self { salt, key })
+    }
+
+    /// Encrypt the plaintext, prefixing it with the given magic.
+    pub(crate) fn seal(&self, magic: &[u8; MAGIC_LEN], plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = XNonce::default();
+        fill_random(&mut nonce)?;
+        let ciphertext = XChaCha20Poly1305::new(&self.key)
+            .encrypt(&nonce, plaintext)
+            .map_err(|e| Error::PlatformFailure(e.to_string().into()))?;
+        let mut result = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+        result.extend_from_slice(magic);
+        result.extend_from_slice(&self.salt);
+        result.extend_from_slice(&nonce);
+        result.extend_from_slice(&ciphertext);
+        Ok(result)
+    }
+
+    /// Decrypt a sealed blob, which must have been sealed with this key.
+    pub(crate) fn open(&self, magic: &[u8; MAGIC_LEN], data: &[u8]) -> Result<Vec<u8>> {
+        if salt_of(magic, data)? != self.salt {
+            return Err(bad_data("was sealed with a different salt"));
+        }
+        let nonce = XNonce::from_slice(&data[MAGIC_LEN + SALT_LEN..HEADER_LEN]);
+        XChaCha20Poly1305::new(&self.key)
+            .decrypt(nonce, &data[HEADER_LEN..])
+            .map_err(|_| bad_data("wrong passphrase or corrupted data"))
+    }
+}
+
+/// Check the magic of a sealed blob and return its salt.
+pub(crate) fn salt_of(magic: &[u8; MAGIC_LEN], data: &[u8]) -> Result<Salt> {
+    if data.len() < HEADER_LEN {
+        return Err(bad_data("is too short"));
+    }
+    if &data[..MAGIC_LEN] != magic {
+        return Err(bad_data("has an unknown format or version"));
+    }
+    let mut salt = [0u8; SALT_LEN];
+    salt.copy_from_slice(&data[MAGIC_LEN..MAGIC_LEN + SALT_LEN]);
+    Ok(salt)
+}
+
+/// Fill the buffer with random bytes from the OS.
+pub(crate) fn fill_random(buf: &mut [u8]) -> Result<()> {
+    getrandom::fill(buf).map_err(|e| Error::PlatformFailure(e.to_string().into()))
+}
+
+/// The error for sealed data that can't be opened. The data itself isn't
+/// included: it's of no use to the caller, and may be large.
+fn bad_data(reason: &str) -> Error {
+    Error::BadDataFormat(Vec::new(), format!("Sealed data {reason}").into())
+}
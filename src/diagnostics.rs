@@ -0,0 +1,168 @@
+/*!
+
+Secret Service health checks.
+
+This module produces a [Diagnosis] of the environment this store runs in:
+whether there is a session bus, who provides the Secret Service on it,
+and what state the service's collections are in. It doesn't use a store's
+shared service connection, so it works even when a store can't be created.
+
+*/
+
+use secret_service::EncryptionType;
+use secret_service::blocking::SecretService;
+use zbus::blocking::Connection;
+use zbus::blocking::fdo::DBusProxy;
+use zbus::names::BusName;
+
+use crate::errors::Cause;
+use crate::metrics::Recorder;
+use crate::service::describe_collections;
+
+/// The well-known bus name of the Secret Service.
+const SECRETS_NAME: &str = "org.freedesktop.secrets";
+
+/// A report on the health of the Secret Service.
+///
+/// Any failure encountered while gathering the report is recorded in
+/// `problems` rather than returned, so that as much of the report as
+/// possible is filled in.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnosis {
+    /// Whether a connection to the session bus could be made.
+    pub bus_reachable: bool,
+    /// The unique bus name of the connection owning `org.freedesktop.secrets`.
+    pub service_owner: Option<String>,
+    /// The process name of the service owner (e.g., `gnome-keyring-d`,
+    /// `kwalletd6`, `keepassxc`), if it could be determined.
+    pub provider: Option<String>,
+    /// Whether the service was already running or could be activated.
+    pub service_available: bool,
+    /// The session encryption algorithm negotiated with the service.
+    pub algorithm: Option<String>,
+    /// Whether the service has a collection with the `default` alias.
+    pub default_collection: bool,
+    /// The status of each collection in the service.
+    pub collections: Vec<CollectionStatus>,
+    /// Human-readable descriptions of the failures encountered.
+    pub problems: Vec<String>,
+}
+
+/// The status of a single collection.
+#[derive(Debug, Clone)]
+pub struct CollectionStatus {
+    pub label: String,
+    pub path: String,
+    /// The well-known aliases (`default`, `session`) that name this collection.
+    pub aliases: Vec<String>,
+    pub locked: bool,
+}
+
+impl std::fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let or_unknown = |v: &Option<String>| v.clone().unwrap_or("unknown".to_string());
+        writeln!(f, "Session bus reachable: {}", self.bus_reachable)?;
+        writeln!(f, "Secret Service available: {}", self.service_available)?;
+        writeln!(f, "Service owner: {}", or_unknown(&self.service_owner))?;
+        writeln!(f, "Service provider: {}", or_unknown(&self.provider))?;
+        writeln!(f, "Negotiated algorithm: {}", or_unknown(&self.algorithm))?;
+        writeln!(f, "Default collection exists: {}", self.default_collection)?;
+        writeln!(f, "Collections: {}", self.collections.len())?;
+        for c in &self.collections {
+            let state = if c.locked { "locked" } else { "unlocked" };
+            write!(f, "  {} ({}): {state}", c.label, c.path)?;
+            if !c.aliases.is_empty() {
+                write!(f, ", aliases: {}", c.aliases.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+        for p in &self.problems {
+            writeln!(f, "Problem: {p}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Examine the session bus and the Secret Service on it.
+pub(crate) fn diagnose() -> Diagnosis {
+    let mut report = Diagnosis::default();
+    let conn = match Connection::session() {
+        Ok(conn) => conn,
+        Err(err) => {
+            let cause = Cause::BusUnavailable(secret_service::Error::Zbus(err));
+            report.problems.push(cause.to_string());
+            return report;
+        }
+    };
+    report.bus_reachable = true;
+    check_owner(&conn, &mut report);
+    let ss = match connect(&conn, &mut report) {
+        Some(ss) => ss,
+        None => return report,
+    };
+    report.service_available = true;
+    // connecting may have activated the service, so look for its owner again
+    if report.service_owner.is_none() {
+        check_owner(&conn, &mut report);
+    }
+    check_collections(&ss, &mut report);
+    report
+}
+
+fn check_owner(conn: &Connection, report: &mut Diagnosis) {
+    let dbus = match DBusProxy::new(conn) {
+        Ok(dbus) => dbus,
+        Err(err) => {
+            report.problems.push(format!("Can't query the bus: {err}"));
+            return;
+        }
+    };
+    let name = BusName::from_static_str(SECRETS_NAME).expect("valid bus name");
+    let owner = match dbus.get_name_owner(name) {
+        Ok(owner) => owner,
+        Err(_) => return,
+    };
+    report.service_owner = Some(owner.to_string());
+    if let Ok(pid) = dbus.get_connection_unix_process_id(owner.into_inner().into()) {
+        report.provider = std::fs::read_to_string(format!("/proc/{pid}/comm"))
+            .ok()
+            .map(|s| s.trim().to_string());
+    }
+}
+
+fn connect<'a>(conn: &Connection, report: &mut Diagnosis) -> Option<SecretService<'a>> {
+    match SecretService::connect_with_existing(EncryptionType::Dh, conn.clone()) {
+        Ok(ss) => {
+            report.algorithm = Some("dh-ietf1024-sha256-aes128-cbc-pkcs7".to_string());
+            return Some(ss);
+        }
+        Err(err) => report.problems.push(Cause::from(err).to_string()),
+    }
+    match SecretService::connect_with_existing(EncryptionType::Plain, conn.clone()) {
+        Ok(ss) => {
+            report.algorithm = Some("plain".to_string());
+            Some(ss)
+        }
+        Err(err) => {
+            report.problems.push(Cause::from(err).to_string());
+            None
+        }
+    }
+}
+
+fn check_collections(ss: &SecretService, report: &mut Diagnosis) {
+    // the diagnosis has its own connection, so its calls aren't counted in any store's metrics
+    match describe_collections(ss, &Recorder::default()) {
+        Ok(collections) => report.collections = collections,
+        Err(err) => report.problems.push(err.to_string()),
+    }
+    report.default_collection = report
+        .collections
+        .iter()
+        .any(|c| c.aliases.iter().any(|a| a == "default"));
+    if !report.default_collection {
+        report
+            .problems
+            .push("There is no default collection".to_string());
+    }
+}
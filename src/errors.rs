@@ -3,18 +3,190 @@
 //!
 
 use secret_service::Error as ServiceError;
+use zbus::DBusError;
 
 use keyring_core::error::Error;
 
+/// The classified cause of a Secret Service failure.
+///
+/// Every [PlatformFailure](Error::PlatformFailure) and
+/// [NoStorageAccess](Error::NoStorageAccess) error produced by this store
+/// wraps one of these, so clients can find it via `Error::source()`
+/// (or more simply via [Cause::of]) and tell the user what to do about it.
+/// The underlying secret-service error (if there is one)
+/// is available as the source of the cause.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Cause {
+    /// There is no D-Bus session bus to connect to.
+    BusUnavailable(ServiceError),
+    /// Nothing owns `org.freedesktop.secrets` on the session bus,
+    /// and it could not be activated.
+    ServiceUnavailable(ServiceError),
+    /// The service doesn't support the session encryption algorithm.
+    UnsupportedAlgorithm(ServiceError),
+    /// There is no collection with the requested alias.
+    NoCollection(ServiceError),
+    /// The object is locked and could not be unlocked.
+    Locked(ServiceError),
+    /// The user dismissed a prompt, or the prompt was abandoned.
+    PromptDismissed(ServiceError),
+    /// The service (or the bus) denied permission for the operation.
+    PermissionDenied(ServiceError),
+    /// Any other failure.
+    Other(ServiceError),
+    /// The credential expired at the given time (in seconds since the Unix epoch).
+    Expired(u64),
+    /// A compare-and-set found a secret other than the expected one.
+    Conflict,
+}
+
+impl Cause {
+    /// Find the cause wrapped by a keyring error, if there is one.
+    pub fn of(err: &Error) -> Option<&Cause> {
+        std::error::Error::source(err).and_then(|e| e.downcast_ref::<Cause>())
+    }
+
+    /// The underlying secret-service error, if the cause came from the service.
+    pub fn service_error(&self) -> Option<&ServiceError> {
+        match self {
+            Cause::BusUnavailable(err)
+            | Cause::ServiceUnavailable(err)
+            | Cause::UnsupportedAlgorithm(err)
+            | Cause::NoCollection(err)
+            | Cause::Locked(err)
+            | Cause::PromptDismissed(err)
+            | Cause::PermissionDenied(err)
+            | Cause::Other(err) => Some(err),
+            Cause::Expired(_) | Cause::Conflict => None,
+        }
+    }
+}
+
+impl From<ServiceError> for Cause {
+    fn from(err: ServiceError) -> Self {
+        match err {
+            ServiceError::Unavailable => {
+                return if session_bus_is_reachable() {
+                    Cause::ServiceUnavailable(err)
+                } else {
+                    Cause::BusUnavailable(err)
+                };
+            }
+            ServiceError::Locked => return Cause::Locked(err),
+            ServiceError::NoResult => return Cause::NoCollection(err),
+            ServiceError::Prompt | ServiceError::PromptDisconnected => {
+                return Cause::PromptDismissed(err);
+            }
+            ServiceError::Zbus(
+                zbus::Error::Address(_)
+                | zbus::Error::InputOutput(_)
+                | zbus::Error::Connection(_, _)
+                | zbus::Error::Handshake(_),
+            ) => return Cause::BusUnavailable(err),
+            _ => {}
+        }
+        match error_name(&err).as_deref() {
+            Some(
+                "org.freedesktop.DBus.Error.ServiceUnknown"
+                | "org.freedesktop.DBus.Error.NameHasNoOwner"
+                | "org.freedesktop.DBus.Error.NoReply",
+            ) => Cause::ServiceUnavailable(err),
+            Some(name) if name.starts_with("org.freedesktop.DBus.Error.Spawn") => {
+                Cause::ServiceUnavailable(err)
+            }
+            Some(
+                "org.freedesktop.DBus.Error.NoServer" | "org.freedesktop.DBus.Error.Disconnected",
+            ) => Cause::BusUnavailable(err),
+            Some("org.freedesktop.DBus.Error.NotSupported") => Cause::UnsupportedAlgorithm(err),
+            Some("org.freedesktop.Secret.Error.IsLocked") => Cause::Locked(err),
+            Some(
+                "org.freedesktop.DBus.Error.AccessDenied"
+                | "org.freedesktop.DBus.Error.AuthFailed"
+                | "org.freedesktop.DBus.Error.InteractiveAuthorizationRequired",
+            ) => Cause::PermissionDenied(err),
+            _ => Cause::Other(err),
+        }
+    }
+}
+
+impl std::fmt::Display for Cause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let summary = match self {
+            Cause::BusUnavailable(_) => "No D-Bus session bus is available",
+            Cause::ServiceUnavailable(_) => {
+                "The Secret Service is not running; start your keyring daemon"
+            }
+            Cause::UnsupportedAlgorithm(_) => {
+                "The Secret Service does not support the requested encryption algorithm"
+            }
+            Cause::NoCollection(_) => "There is no collection with the requested alias",
+            Cause::Locked(_) => "The keyring is locked",
+            Cause::PromptDismissed(_) => "The keyring prompt was dismissed",
+            Cause::PermissionDenied(_) => "Permission to access the keyring was denied",
+            Cause::Other(_) => "The Secret Service failed",
+            Cause::Expired(at) => return write!(f, "The credential expired at {at}"),
+            Cause::Conflict => {
+                "The secret is not the expected one (it was changed by someone else)"
+            }
+        };
+        match self.service_error() {
+            Some(err) => write!(f, "{summary} ({err})"),
+            None => write!(f, "{summary}"),
+        }
+    }
+}
+
+impl std::error::Error for Cause {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.service_error()
+            .map(|err| err as &(dyn std::error::Error + 'static))
+    }
+}
+
 /// Map underlying secret-service errors to keyring errors with
 /// appropriate annotation.
+///
+/// Errors that indicate the item being operated on has vanished
+/// (for example, because it was deleted concurrently) become
+/// [NoEntry](Error::NoEntry) errors.
 pub fn decode_error(err: ServiceError) -> Error {
-    match err {
-        ServiceError::Locked => no_access(err),
-        ServiceError::NoResult => no_access(err),
-        ServiceError::Prompt => no_access(err),
-        _ => platform_failure(err),
+    if is_vanished(&err) {
+        return Error::NoEntry;
     }
+    match Cause::from(err) {
+        cause @ (Cause::Other(_) | Cause::UnsupportedAlgorithm(_)) => {
+            Error::PlatformFailure(Box::new(cause))
+        }
+        cause => Error::NoStorageAccess(Box::new(cause)),
+    }
+}
+
+/// The error returned when reading the secret of an item that
+/// expired at the given time (in seconds since the Unix epoch).
+///
+/// This is a [NoStorageAccess](Error::NoStorageAccess) error whose cause is
+/// [Expired](Cause::Expired); use [is_expired] to check for it.
+pub fn expired(at: u64) -> Error {
+    Error::NoStorageAccess(Box::new(Cause::Expired(at)))
+}
+
+/// Whether an error returned by a credential operation says the credential has expired.
+pub fn is_expired(err: &Error) -> bool {
+    matches!(Cause::of(err), Some(Cause::Expired(_)))
+}
+
+/// The error returned when a compare-and-set finds a secret other than the expected one.
+///
+/// This is a [PlatformFailure](Error::PlatformFailure) error whose cause is
+/// [Conflict](Cause::Conflict); use [is_conflict] to check for it.
+pub fn conflict() -> Error {
+    Error::PlatformFailure(Box::new(Cause::Conflict))
+}
+
+/// Whether an error returned by a compare-and-set says the secret was changed by someone else.
+pub fn is_conflict(err: &Error) -> bool {
+    matches!(Cause::of(err), Some(Cause::Conflict))
 }
 
 pub fn empty_target() -> Error {
@@ -22,13 +194,53 @@ pub fn empty_target() -> Error {
 }
 
 pub fn platform_failure(err: ServiceError) -> Error {
-    Error::PlatformFailure(wrap(err))
+    Error::PlatformFailure(Box::new(Cause::from(err)))
 }
 
-fn no_access(err: ServiceError) -> Error {
-    Error::NoStorageAccess(wrap(err))
+/// Whether a session bus can be connected to.
+///
+/// The secret-service crate reports both a missing session bus and a bus
+/// on which no provider is running as [Unavailable](ServiceError::Unavailable),
+/// so the bus is checked directly to tell them apart.
+fn session_bus_is_reachable() -> bool {
+    zbus::blocking::Connection::session().is_ok()
 }
 
-fn wrap(err: ServiceError) -> Box<dyn std::error::Error + Send + Sync> {
-    Box::new(err)
+/// Whether the error says the target object no longer exists.
+///
+/// Errors saying that the object doesn't have the method or interface
+/// called are not included: they mean the service doesn't implement
+/// the call, not that the object was deleted.
+fn is_vanished(err: &ServiceError) -> bool {
+    matches!(
+        error_name(err).as_deref(),
+        Some(
+            "org.freedesktop.DBus.Error.UnknownObject"
+                | "org.freedesktop.Secret.Error.NoSuchObject"
+        )
+    )
+}
+
+/// The D-Bus error name carried by a secret-service error, if any.
+fn error_name(err: &ServiceError) -> Option<String> {
+    match err {
+        ServiceError::Zbus(err) => zbus_error_name(err),
+        ServiceError::ZbusFdo(err) => fdo_error_name(err),
+        _ => None,
+    }
+}
+
+fn zbus_error_name(err: &zbus::Error) -> Option<String> {
+    match err {
+        zbus::Error::MethodError(name, _, _) => Some(name.to_string()),
+        zbus::Error::FDO(err) => fdo_error_name(err),
+        _ => None,
+    }
+}
+
+fn fdo_error_name(err: &zbus::fdo::Error) -> Option<String> {
+    match err {
+        zbus::fdo::Error::ZBus(err) => zbus_error_name(err),
+        err => Some(err.name().to_string()),
+    }
 }
@@ -0,0 +1,506 @@
+/*!
+
+Encrypted file fallback for when the Secret Service is unavailable.
+
+When a store is configured with a fallback (see [Store](crate::Store)) and
+the Secret Service can't be reached, or has no default collection, the store
+keeps its items in a single file encrypted with a key derived from the
+configured passphrase. The file is only readable by its owner.
+
+Items in the file have the same attributes and labels they would have in
+the Secret Service, and entries built or found by a fallback store behave
+just like those of a Secret Service store, but targets are treated only as
+attributes (there are no collections) and ephemeral entries are not supported.
+
+The whole file is read and rewritten on each change, under a lock that
+serializes access from the credentials of one store. Concurrent writes to
+the same file from different stores or processes are not coordinated.
+
+*/
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use keyring_core::Entry;
+use keyring_core::api::{Credential, CredentialApi};
+use keyring_core::error::{Error, Result};
+
+use crate::crypto::{SealingKey, fill_random, salt_of};
+use crate::service::Service;
+use crate::store::Backend;
+
+pub(crate) const MAGIC: &[u8; 8] = b"KRSSFB01";
+
+/// Choose a store's backend according to its configuration.
+///
+/// See [Store::new_with_configuration](crate::Store::new_with_configuration)
+/// for the configuration keys.
+pub(crate) fn connect(config: &HashMap<&str, &str>) -> Result<Backend> {
+    for key in config.keys() {
+        if !["fallback", "fallback-path", "fallback-passphrase"].contains(key) {
+            return Err(Error::NotSupportedByStore(format!(
+                "Unknown Secret Service configuration key: {key}"
+            )));
+        }
+    }
+    let mode = config.get("fallback").copied().unwrap_or("never");
+    if !["never", "when-unavailable", "always"].contains(&mode) {
+        return Err(Error::Invalid(
+            "fallback".to_string(),
+            "must be 'never', 'when-unavailable', or 'always'".to_string(),
+        ));
+    }
+    if mode == "never" {
+        return Ok(Backend::Service(Arc::new(Service::new()?)));
+    }
+    let passphrase = match config.get("fallback-passphrase") {
+        Some(p) if !p.is_empty() => *p,
+        _ => {
+            return Err(Error::Invalid(
+                "fallback-passphrase".to_string(),
+                "must be specified when a fallback is allowed".to_string(),
+            ));
+        }
+    };
+    let path = match config.get("fallback-path") {
+        Some(&"") => {
+            return Err(Error::Invalid(
+                "fallback-path".to_string(),
+                "cannot be empty".to_string(),
+            ));
+        }
+        Some(p) => PathBuf::from(p),
+        None => FileStore::default_path(),
+    };
+    if mode == "when-unavailable"
+        && let Ok(ss) = Service::new()
+        && ss.has_default_collection()
+    {
+        return Ok(Backend::Service(Arc::new(ss)));
+    }
+    Ok(Backend::File(Arc::new(FileStore::new(&path, passphrase)?)))
+}
+
+/// An item stored in the fallback file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FileItem {
+    id: String,
+    label: String,
+    attributes: HashMap<String, String>,
+    secret: Vec<u8>,
+}
+
+/// The encrypted file that holds a fallback store's items.
+pub(crate) struct FileStore {
+    pub(crate) path: PathBuf,
+    passphrase: String,
+    key: Mutex<Option<SealingKey>>,
+}
+
+impl FileStore {
+    /// Open (or prepare to create) the fallback file at the given path.
+    ///
+    /// If the file exists, it must be readable with the given passphrase.
+    pub(crate) fn new(path: &Path, passphrase: &str) -> Result<Self> {
+        let store = Self {
+            path: path.to_path_buf(),
+            passphrase: passphrase.to_string(),
+            key: Mutex::new(None),
+        };
+        store.with_items(|_| Ok(((), false)))?;
+        Ok(store)
+    }
+
+    /// The default location of the fallback file.
+    pub(crate) fn default_path() -> PathBuf {
+        let base = match std::env::var_os("XDG_DATA_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => {
+                let home = std::env::var_os("HOME").unwrap_or_default();
+                PathBuf::from(home).join(".local").join("share")
+            }
+        };
+        base.join("keyring").join("secret-service-fallback.dat")
+    }
+
+    /// Run an operation on the items in the file.
+    ///
+    /// The operation returns its result and whether it changed the items;
+    /// changed items are written back to the file.
+    fn with_items<T>(&self, op: impl FnOnce(&mut Vec<FileItem>) -> Result<(T, bool)>) -> Result<T> {
+        let mut key = self
+            .key
+            .lock()
+            .expect("Mutex failure in credential store: please report a bug");
+        let mut items: Vec<FileItem> = match std::fs::read(&self.path) {
+            Ok(data) => {
+                let salt = salt_of(MAGIC, &data)?;
+                if key.as_ref().map(|k| k.salt != salt).unwrap_or(true) {
+                    *key = Some(SealingKey::with_salt(&self.passphrase, salt)?);
+                }
+                let plain = key.as_ref().unwrap().open(MAGIC, &data)?;
+                // the decrypted data holds every stored secret, so neither it nor
+                // the parse error (which can quote it) is put in the error
+                serde_json::from_slice(&plain).map_err(|e| {
+                    Error::BadStoreFormat(format!(
+                        "{}: malformed (at line {}, column {})",
+                        self.path.display(),
+                        e.line(),
+                        e.column()
+                    ))
+                })?
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(Error::PlatformFailure(Box::new(err))),
+        };
+        let (result, changed) = op(&mut items)?;
+        if changed {
+            if key.is_none() {
+                *key = Some(SealingKey::new(&self.passphrase)?);
+            }
+            let plain = serde_json::to_vec(&items).map_err(|e| Error::PlatformFailure(e.into()))?;
+            let data = key.as_ref().unwrap().seal(MAGIC, &plain)?;
+            self.write(&data)
+                .map_err(|e| Error::PlatformFailure(Box::new(e)))?;
+        }
+        Ok(result)
+    }
+
+    /// Atomically replace the file's contents.
+    fn write(&self, data: &[u8]) -> std::io::Result<()> {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let temp = self.path.with_extension("tmp");
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&temp)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        std::fs::rename(&temp, &self.path)
+    }
+
+    pub(crate) fn find_matching_items(
+        &self,
+        attributes: &HashMap<&str, &str>,
+    ) -> Result<Vec<String>> {
+        self.with_items(|items| {
+            let found = items
+                .iter()
+                .filter(|i| {
+                    attributes
+                        .iter()
+                        .all(|(k, v)| i.attributes.get(*k).map(|a| a == v).unwrap_or(false))
+                })
+                .map(|i| i.id.clone())
+                .collect();
+            Ok((found, false))
+        })
+    }
+
+    fn create_item(
+        &self,
+        label: &str,
+        attributes: HashMap<&str, &str>,
+        secret: &[u8],
+    ) -> Result<()> {
+        let mut id = [0u8; 16];
+        fill_random(&mut id)?;
+        let item = FileItem {
+            id: id.iter().map(|b| format!("{b:02x}")).collect(),
+            label: label.to_string(),
+            attributes: attributes
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            secret: secret.to_vec(),
+        };
+        self.with_items(|items| {
+            items.push(item);
+            Ok(((), true))
+        })
+    }
+
+    fn get_item(&self, id: &str) -> Result<FileItem> {
+        self.with_items(|items| {
+            let item = items.iter().find(|i| i.id == id).ok_or(Error::NoEntry)?;
+            Ok((item.clone(), false))
+        })
+    }
+
+    fn update_item(&self, id: &str, update: impl FnOnce(&mut FileItem)) -> Result<()> {
+        self.with_items(|items| {
+            let item = items
+                .iter_mut()
+                .find(|i| i.id == id)
+                .ok_or(Error::NoEntry)?;
+            update(item);
+            Ok(((), true))
+        })
+    }
+
+    fn delete_item(&self, id: &str) -> Result<()> {
+        self.with_items(|items| {
+            let index = items
+                .iter()
+                .position(|i| i.id == id)
+                .ok_or(Error::NoEntry)?;
+            items.remove(index);
+            Ok(((), true))
+        })
+    }
+}
+
+/// The specifier for an item in the fallback file.
+///
+/// This is the fallback counterpart of [Specifier](crate::cred::Specifier).
+pub struct FileSpecifier {
+    fs: Arc<FileStore>,
+    pub label: String,
+    pub target: Option<String>,
+    pub service: String,
+    pub user: String,
+}
+
+impl std::fmt::Debug for FileSpecifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileSpecifier")
+            .field("file", &self.fs.path)
+            .field("label", &self.label)
+            .field("target", &self.target)
+            .field("service", &self.service)
+            .field("user", &self.user)
+            .finish()
+    }
+}
+
+impl FileSpecifier {
+    pub(crate) fn new(
+        fs: Arc<FileStore>,
+        label: Option<&str>,
+        target: Option<&str>,
+        service: &str,
+        user: &str,
+    ) -> Arc<Self> {
+        let label = match label {
+            None => format!("keyring:{user}@{service}"),
+            Some(l) => l.to_string(),
+        };
+        Arc::new(Self {
+            fs,
+            label,
+            target: target.map(|s| s.to_string()),
+            service: service.to_string(),
+            user: user.to_string(),
+        })
+    }
+
+    /// Returns the label on an existing, matching item.
+    pub fn get_label(&self) -> Result<String> {
+        let id = self.get_unique_item()?;
+        Ok(self.fs.get_item(&id)?.label)
+    }
+
+    /// Sets the label on an existing, matching item.
+    pub fn set_label(&self, label: &str) -> Result<()> {
+        let id = self.get_unique_item()?;
+        self.fs.update_item(&id, |i| i.label = label.to_string())
+    }
+
+    fn get_unique_item(&self) -> Result<String> {
+        let ids = self.fs.find_matching_items(&self.search_attributes())?;
+        match ids.len() {
+            0 => Err(Error::NoEntry),
+            1 => Ok(ids[0].clone()),
+            _ => {
+                let entries = ids
+                    .into_iter()
+                    .map(|id| Entry::new_with_credential(FileWrapper::new(self.fs.clone(), id)))
+                    .collect();
+                Err(Error::Ambiguous(entries))
+            }
+        }
+    }
+
+    fn search_attributes(&self) -> HashMap<&str, &str> {
+        let mut result: HashMap<&str, &str> = HashMap::new();
+        result.insert("service", self.service.as_str());
+        result.insert("username", self.user.as_str());
+        if let Some(target) = &self.target {
+            result.insert("target", target.as_str());
+        }
+        result
+    }
+}
+
+impl CredentialApi for FileSpecifier {
+    /// See the keyring-core API docs.
+    fn set_secret(&self, secret: &[u8]) -> Result<()> {
+        match self.get_unique_item() {
+            Ok(id) => return self.fs.update_item(&id, |i| i.secret = secret.to_vec()),
+            Err(Error::NoEntry) => {}
+            Err(err) => return Err(err),
+        }
+        self.fs
+            .create_item(&self.label, self.search_attributes(), secret)
+    }
+
+    /// See the keyring-core API docs.
+    fn get_secret(&self) -> Result<Vec<u8>> {
+        let id = self.get_unique_item()?;
+        Ok(self.fs.get_item(&id)?.secret)
+    }
+
+    /// See the keyring-core API docs.
+    fn get_attributes(&self) -> Result<HashMap<String, String>> {
+        let id = self.get_unique_item()?;
+        Ok(self.fs.get_item(&id)?.attributes)
+    }
+
+    /// See the keyring-core API docs.
+    fn update_attributes(&self, attributes: &HashMap<&str, &str>) -> Result<()> {
+        for key in attributes.keys() {
+            if *key == "target" || *key == "service" || *key == "username" {
+                return Err(Error::Invalid(
+                    key.to_string(),
+                    "cannot be updated".to_string(),
+                ));
+            }
+        }
+        let id = self.get_unique_item()?;
+        self.fs
+            .update_item(&id, |i| merge_attributes(i, attributes))
+    }
+
+    /// See the keyring-core API docs.
+    fn delete_credential(&self) -> Result<()> {
+        let id = self.get_unique_item()?;
+        self.fs.delete_item(&id)
+    }
+
+    /// See the keyring-core API docs.
+    fn get_credential(&self) -> Result<Option<Arc<Credential>>> {
+        let id = self.get_unique_item()?;
+        Ok(Some(FileWrapper::new(self.fs.clone(), id)))
+    }
+
+    /// See the keyring-core API docs.
+    fn get_specifiers(&self) -> Option<(String, String)> {
+        Some((self.service.clone(), self.user.clone()))
+    }
+
+    /// See the keyring-core API docs.
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    /// See the keyring-core API docs.
+    fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// A wrapper around an item in the fallback file.
+///
+/// This is the fallback counterpart of [Wrapper](crate::cred::Wrapper).
+pub struct FileWrapper {
+    fs: Arc<FileStore>,
+    id: String,
+}
+
+impl std::fmt::Debug for FileWrapper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileWrapper")
+            .field("file", &self.fs.path)
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
+impl FileWrapper {
+    pub(crate) fn new(fs: Arc<FileStore>, id: String) -> Arc<Self> {
+        Arc::new(Self { fs, id })
+    }
+
+    /// Returns the label on the wrapped item.
+    pub fn get_label(&self) -> Result<String> {
+        Ok(self.fs.get_item(&self.id)?.label)
+    }
+
+    /// Sets the label on the wrapped item.
+    pub fn set_label(&self, label: &str) -> Result<()> {
+        self.fs
+            .update_item(&self.id, |i| i.label = label.to_string())
+    }
+}
+
+impl CredentialApi for FileWrapper {
+    /// See the keyring-core API docs.
+    fn set_secret(&self, secret: &[u8]) -> Result<()> {
+        self.fs
+            .update_item(&self.id, |i| i.secret = secret.to_vec())
+    }
+
+    /// See the keyring-core API docs.
+    fn get_secret(&self) -> Result<Vec<u8>> {
+        Ok(self.fs.get_item(&self.id)?.secret)
+    }
+
+    /// See the keyring-core API docs.
+    fn get_attributes(&self) -> Result<HashMap<String, String>> {
+        Ok(self.fs.get_item(&self.id)?.attributes)
+    }
+
+    /// See the keyring-core API docs.
+    fn update_attributes(&self, attributes: &HashMap<&str, &str>) -> Result<()> {
+        self.fs
+            .update_item(&self.id, |i| merge_attributes(i, attributes))
+    }
+
+    /// See the keyring-core API docs.
+    fn delete_credential(&self) -> Result<()> {
+        self.fs.delete_item(&self.id)
+    }
+
+    /// See the keyring-core API docs.
+    fn get_credential(&self) -> Result<Option<Arc<Credential>>> {
+        self.fs.get_item(&self.id)?;
+        Ok(None)
+    }
+
+    /// See the keyring-core API docs.
+    fn get_specifiers(&self) -> Option<(String, String)> {
+        let attributes = self.fs.get_item(&self.id).ok()?.attributes;
+        if let Some(service) = attributes.get("service")
+            && let Some(user) = attributes.get("username")
+        {
+            return Some((service.to_string(), user.to_string()));
+        }
+        None
+    }
+
+    /// See the keyring-core API docs.
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    /// See the keyring-core API docs.
+    fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+fn merge_attributes(item: &mut FileItem, attributes: &HashMap<&str, &str>) {
+    for (k, v) in attributes.iter() {
+        item.attributes.insert(k.to_string(), v.to_string());
+    }
+}
@@ -0,0 +1,116 @@
+/*!
+
+Persistent item handles.
+
+Entries built by a store find their items by searching on attributes, which
+the Secret Service may answer with a prompt to unlock. An application that
+has already found an item can instead keep a _handle_ to it, and use the
+handle to get an entry for the same item later (even in a later run) without
+searching.
+
+A handle is a string of the form `<path>#<fingerprint>`, where `<path>` is the
+item's D-Bus object path and `<fingerprint>` is 16 hexadecimal digits. The
+fingerprint is computed (with the 64-bit FNV-1a hash) from the item's creation
+time and its identifying attributes: those that hold its service and user (as
+laid out by its [profile](crate::profile)), its `target` attribute, its
+`xdg:schema` attribute (if its profile has a schema), and its extra identifying
+attributes (those of its profile's schema, and those it was created with
+by `attribute:` modifiers), in order of name. Since paths can be
+reused once an item is deleted, [Store::entry_from_handle](crate::Store::entry_from_handle)
+checks that the item at the handle's path still has the same fingerprint,
+so a handle to an item that has been deleted, or replaced by a new item with
+the same path, gives a [NoEntry](keyring_core::Error::NoEntry) error. (Creation
+times have a resolution of one second, so a replacement with the same
+identifying attributes created within the same second isn't detected.)
+Changes to an item's secret, label, or other attributes don't change its
+fingerprint. The format (and the fingerprint computation) will not change in
+future versions of this crate.
+
+Get a handle from [Wrapper::handle](crate::cred::Wrapper::handle). Neither
+making a handle nor getting an entry from one unlocks the item.
+
+*/
+
+use std::collections::HashMap;
+
+use zbus::zvariant::OwnedObjectPath;
+
+use keyring_core::{Error, Result};
+
+use crate::cred::identity_of;
+use crate::profile::SCHEMA_ATTRIBUTE;
+
+/// Parse an item path.
+pub(crate) fn parse_path(path: &str) -> Result<OwnedObjectPath> {
+    OwnedObjectPath::try_from(path).map_err(|_| {
+        Error::Invalid(
+            "path".to_string(),
+            format!("'{path}' is not a D-Bus object path"),
+        )
+    })
+}
+
+/// Make a handle from an item's path and fingerprint.
+pub(crate) fn format(path: &OwnedObjectPath, fingerprint: u64) -> String {
+    format!("{}#{fingerprint:016x}", path.as_str())
+}
+
+/// Split a handle into an item's path and fingerprint.
+pub(crate) fn parse(handle: &str) -> Result<(OwnedObjectPath, u64)> {
+    let invalid = || {
+        Error::Invalid(
+            "handle".to_string(),
+            format!("'{handle}' is not of the form <path>#<fingerprint>"),
+        )
+    };
+    let (path, fingerprint) = handle.rsplit_once('#').ok_or_else(invalid)?;
+    if fingerprint.len() != 16 {
+        return Err(invalid());
+    }
+    let fingerprint = u64::from_str_radix(fingerprint, 16).map_err(|_| invalid())?;
+    let path = OwnedObjectPath::try_from(path).map_err(|_| invalid())?;
+    Ok((path, fingerprint))
+}
+
+/// Compute the fingerprint of an item from its creation time and attributes.
+pub(crate) fn fingerprint(created: u64, attributes: &HashMap<String, String>) -> u64 {
+    let (profile, extras) = identity_of(attributes);
+    let mut keys = vec![
+        profile.service_attribute(),
+        profile.user_attribute(),
+        "target",
+    ];
+    if profile.schema().is_some() {
+        keys.push(SCHEMA_ATTRIBUTE);
+    }
+    keys.extend(extras.keys().map(String::as_str));
+    let mut hash = Fnv1a::new();
+    hash.write(&created.to_be_bytes());
+    for key in keys {
+        // a separator byte that can't occur in UTF-8 keeps fields from running together
+        hash.write(&[0xff]);
+        if let Some(value) = attributes.get(key) {
+            hash.write(key.as_bytes());
+            hash.write(&[0xff]);
+            hash.write(value.as_bytes());
+        }
+    }
+    hash.0
+}
+
+/// The 64-bit FNV-1a hash, used because (unlike the standard library's
+/// hashers) its results are guaranteed not to change.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
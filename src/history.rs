@@ -0,0 +1,179 @@
+/*!
+
+Secret history.
+
+An entry built with the `history` modifier (whose value is a positive number N)
+keeps up to N previous versions of its secret. Whenever the secret of the entry's
+item is changed, the previous secret is first archived into a new history item
+in the same collection. The credential's item and its history items share a
+generated `keyring:history-id` attribute, and each history item also has a
+`keyring:history-version` attribute (numbered upward from 1) and a
+`keyring:history-archived` attribute (when it was archived, in seconds since the
+Unix epoch). History items have no `service` or `username` attribute, so entries
+never find them. The attributes are namespaced so that items written by other
+applications are never mistaken for history (or deleted along with it).
+
+Once there are more than N history items, the oldest are deleted. The versions
+kept can be listed with [list_versions](crate::cred::Specifier::list_versions),
+read with [get_version](crate::cred::Specifier::get_version), and restored with
+[rollback](crate::cred::Specifier::rollback). Deleting a credential also deletes
+its history, as do [bulk deletion](crate::bulk) and
+[sweeping](crate::Store::sweep_expired) of expired items.
+
+*/
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use zbus::zvariant::OwnedObjectPath;
+
+use keyring_core::{Error, Result};
+
+use crate::cred::now;
+use crate::service::Exclusive;
+
+/// The attribute that links a credential's item to its history items.
+pub const HISTORY_ID_ATTRIBUTE: &str = "keyring:history-id";
+
+/// The attribute that holds a history item's version number.
+pub const HISTORY_VERSION_ATTRIBUTE: &str = "keyring:history-version";
+
+/// The attribute that holds when a history item was archived.
+pub const HISTORY_ARCHIVED_ATTRIBUTE: &str = "keyring:history-archived";
+
+/// A previous version of a credential's secret.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretVersion {
+    /// The version number; higher numbers are more recent.
+    pub version: u32,
+    /// When the version was archived, in seconds since the Unix epoch.
+    pub archived: u64,
+}
+
+/// Archive the secret of an item that's about to be replaced by the given one,
+/// then delete all but the newest `keep` versions.
+///
+/// Nothing is archived if the secret isn't changing.
+pub(crate) fn archive(
+    ss: &Exclusive,
+    path: &OwnedObjectPath,
+    replacement: &[u8],
+    keep: usize,
+) -> Result<()> {
+    let secret = ss.get_secret(path)?;
+    if secret == replacement {
+        return Ok(());
+    }
+    let id = match ss.get_attributes(path)?.remove(HISTORY_ID_ATTRIBUTE) {
+        Some(id) => id,
+        None => {
+            let id = generate_id();
+            ss.update_attributes(path, &HashMap::from([(HISTORY_ID_ATTRIBUTE, id.as_str())]))?;
+            id
+        }
+    };
+    let versions = find_versions(ss, &id)?;
+    let version = versions
+        .first()
+        .map(|(v, _)| v.version + 1)
+        .unwrap_or(1)
+        .to_string();
+    let archived = now().to_string();
+    let label = format!("{} (version {version})", ss.get_label(path)?);
+    let attributes = HashMap::from([
+        (HISTORY_ID_ATTRIBUTE, id.as_str()),
+        (HISTORY_VERSION_ATTRIBUTE, version.as_str()),
+        (HISTORY_ARCHIVED_ATTRIBUTE, archived.as_str()),
+    ]);
+    ss.create_sibling_item(path, &label, attributes, &secret)?;
+    // the new version is now the newest, so keep one fewer of the older ones
+    for (_, old) in versions.iter().skip(keep.saturating_sub(1)) {
+        ss.delete(old)?;
+    }
+    Ok(())
+}
+
+/// List the versions kept for an item, newest first.
+pub(crate) fn list_versions(ss: &Exclusive, path: &OwnedObjectPath) -> Result<Vec<SecretVersion>> {
+    let versions = match history_id(ss, path)? {
+        Some(id) => find_versions(ss, &id)?,
+        None => Vec::new(),
+    };
+    Ok(versions.into_iter().map(|(v, _)| v).collect())
+}
+
+/// Get the secret of one of the versions kept for an item.
+pub(crate) fn get_version(ss: &Exclusive, path: &OwnedObjectPath, version: u32) -> Result<Vec<u8>> {
+    let Some(id) = history_id(ss, path)? else {
+        return Err(Error::NoEntry);
+    };
+    let versions = find_versions(ss, &id)?;
+    match versions.iter().find(|(v, _)| v.version == version) {
+        Some((_, p)) => ss.get_secret(p),
+        None => Err(Error::NoEntry),
+    }
+}
+
+/// Delete all the versions kept for an item.
+pub(crate) fn delete_history(ss: &Exclusive, path: &OwnedObjectPath) -> Result<()> {
+    delete_versions(ss, &ss.get_attributes(path)?).map(|_| ())
+}
+
+/// Delete all the versions kept for the item with the given attributes,
+/// returning the paths of the history items that were deleted.
+///
+/// Nothing is deleted if the attributes are those of a history item
+/// (or of an item without history).
+pub(crate) fn delete_versions(
+    ss: &Exclusive,
+    attributes: &HashMap<String, String>,
+) -> Result<Vec<OwnedObjectPath>> {
+    let mut deleted = Vec::new();
+    if attributes.contains_key(HISTORY_VERSION_ATTRIBUTE) {
+        return Ok(deleted);
+    }
+    if let Some(id) = attributes.get(HISTORY_ID_ATTRIBUTE) {
+        for (_, p) in find_versions(ss, id)? {
+            ss.delete(&p)?;
+            deleted.push(p);
+        }
+    }
+    Ok(deleted)
+}
+
+fn history_id(ss: &Exclusive, path: &OwnedObjectPath) -> Result<Option<String>> {
+    Ok(ss.get_attributes(path)?.remove(HISTORY_ID_ATTRIBUTE))
+}
+
+/// Find the history items with the given id, newest first.
+fn find_versions(ss: &Exclusive, id: &str) -> Result<Vec<(SecretVersion, OwnedObjectPath)>> {
+    let mut versions = Vec::new();
+    for path in ss.find_matching_items(&HashMap::from([(HISTORY_ID_ATTRIBUTE, id)]))? {
+        let attributes = ss.get_attributes(&path)?;
+        // the credential's own item has the id but no version
+        let Some(version) = attributes
+            .get(HISTORY_VERSION_ATTRIBUTE)
+            .and_then(|v| v.parse().ok())
+        else {
+            continue;
+        };
+        let archived = attributes
+            .get(HISTORY_ARCHIVED_ATTRIBUTE)
+            .and_then(|t| t.parse().ok())
+            .unwrap_or(0);
+        versions.push((SecretVersion { version, archived }, path));
+    }
+    versions.sort_by_key(|(v, _)| std::cmp::Reverse(v.version));
+    Ok(versions)
+}
+
+/// Generate an id that is, for all practical purposes, unique.
+fn generate_id() -> String {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{nanos:x}-{:x}-{count:x}", std::process::id())
+}
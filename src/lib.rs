@@ -19,11 +19,67 @@ user's login collection). Specifying a `target` modifier when creating an entry
 will create a new collection named by the target and the item for the entry will
 be created in that collection.
 
+Targets are normally matched against collection labels. Since labels aren't
+unique, and users can rename collections in their Secret Service UI, a target
+can instead name a Secret Service _alias_ by using the form `alias:<name>`.
+Such a target is resolved by asking the service which collection has that alias,
+and if there is none then a new collection labeled `<name>` is created and given
+that alias. The target `default` is shorthand for `alias:default`, and the target
+`alias:session` names the service's in-memory session collection, whose items
+are discarded when the user logs out. (The session collection cannot be created
+or deleted by this store.)
+
+If you want an item to be stored only until the user logs out (for example, a
+short-lived access token) but searched for just like other items, specify the
+modifier `ephemeral` with the value `true` when creating its entry. New items
+for such entries are created in the session collection, but no `target`
+attribute is added to them. The `ephemeral` modifier cannot be combined with
+a `target` modifier.
+
+If a credential (say, an access token) should only be used for a limited time,
+specify the modifier `expires` when creating its entry, with the value being the
+expiry time in seconds since the Unix epoch. Setting the secret of such an
+entry records the expiry time in the item's `keyring:expires` attribute, and reading the
+secret of an expired item returns an error for which [errors::is_expired] is true.
+[Store::sweep_expired] deletes (or lists) the expired items. (The attribute
+is namespaced so that items written by other applications, which may have
+an `expires` attribute of their own, are never treated as expired.)
+
+If you want to be able to undo a bad update to a credential's secret, specify
+the modifier `history` when creating its entry, with the value being the number
+of previous versions of the secret to keep. See the [history] module for details.
+
 This implementation controls the following attributes on items:
 
 - `service` (required & taken from the `service` parameter to the entry creation call)
 - `username` (required & taken from the `user` parameter to the entry creation call)
 - `target` (optional & taken from the `target` modifier in the entry creation call)
+- `keyring:expires` (optional & taken from the `expires` modifier in the entry creation call)
+- `keyring:history-id` (optional & added when the entry has a `history` modifier)
+- `keyring:schema-version` (optional & set on [structured](structured) credentials)
+- `keyring:identifying` (optional & names the entry's extra identifying attributes, see below)
+
+If the service, user, and target aren't enough to identify a credential (say,
+it's for a particular host, port, and protocol), specify a modifier named
+`attribute:<name>` for each extra identifying attribute, with the attribute's value
+as the modifier's value. For example, an entry built with the modifier `attribute:host`
+set to `example.com` searches for (and creates) items with a `host` attribute
+of `example.com`, in addition to their `service` and `username` attributes.
+An entry's identifying attributes can't be updated through it, and can't be
+any of the attributes listed above. When an entry creates an item, the names of
+its extra identifying attributes are recorded in the item's `keyring:identifying`
+attribute, so that [item handles](#item-handles) tell apart items that differ
+only in those attributes, and so that
+[Wrapper::to_specifier](cred::Wrapper::to_specifier) gives an entry that finds
+just that item.
+
+Items kept by other applications may use different attributes for the
+service and user. An entry built with a `profile` modifier reads and writes
+items laid out as the named profile says: for example, the `network-password`
+profile uses the layout of GNOME's network passwords, so the passwords that
+GNOME applications have saved for network resources can be shared, and the
+`keytar` profile shares the items of Electron applications and VS Code.
+See the [profile] module for details.
 
 In addition, when creating a new item, this implementation assigns
 the created item a `label` property (for use in Secret Service UI). If the
@@ -32,11 +88,16 @@ as the label. Otherwise, the label is set to the Rust-formatted string:
 `keyring:{user}@{service}`.
 
 Client code is allowed to retrieve and to set all attributes _except_ the
-three that are controlled by this implementation. The label is accessible
+seven that are controlled by this implementation. The label is accessible
 and settable via credential-level calls, but not via entry-level calls.
 The example program in this crate shows how to get at the credential object
 in an entry and use its API.
 
+Although the `target` attribute can't be updated, an existing item can be moved
+to another collection (with its `target` attribute updated to match) by
+[Specifier::retarget](cred::Specifier::retarget) or
+[Wrapper::move_to_collection](cred::Wrapper::move_to_collection).
+
 ## Ambiguity
 
 Existing items are always searched for at the service level, which means all
@@ -44,15 +105,70 @@ collections are searched. The search attributes used are `service` (set from the
 entry service) and `username` (set from the entry user). In addition, if a
 `target` modifier was specified in the creation call of an entry, the `target`
 attribute is also used in the search for that entry: this allows items with the
-same service and user in different collections to be distinguished.
+same service and user in different collections to be distinguished. Similarly,
+an entry's extra identifying attributes are used in the search for it.
 
 Note that existing items created or updated by 3rd party applications may have
 additional attributes; such items will be found when searching for items with
 the same service and user.
 
+## Migration
+
+The [migrate] module copies entries from any other keyring-core credential store
+(for example, one that's file- or keyutils-based) into a store of this type.
+
+## URIs
+
+An entry can be named by a single string, such as
+`secret-service://alias%3Asession/my-service/alice?label=My%20Service`,
+that gives its target, service, user, and other modifiers.
+[Store::entry_from_uri] builds the entry named by such a URI, and the
+`Display` implementation of specifiers (and [Wrapper::uri](cred::Wrapper::uri))
+produce them.
+See the [uri] module for details.
+
+## Secret references
+
+[Store::resolve_secrets] replaces placeholders such as
+`${secret-service:my-service/alice}` in a text (say, a configuration file)
+with the secrets of the entries they name. See the [resolve] module for details.
+
+## Item handles
+
+Entries find their items by searching, which may prompt the user to unlock
+them. [Wrapper::handle](cred::Wrapper::handle) gives a string that can be kept
+(say, across runs of an application) and later passed to
+[Store::entry_from_handle] to get an entry for the same item without searching.
+See the [handle] module for details.
+
+## Bulk deletion
+
+[Store::delete_matching] deletes all the items that match a search spec and
+a [bulk::DeleteFilter] of label and attribute globs, without prompting to unlock
+locked items. It's useful for cleaning up credentials leaked by test suites.
+
+## Batched operations
+
+[Store::get_secrets] and [Store::set_secrets] read and write the secrets of many
+entries at once, with at most one unlock prompt for the whole batch, and return
+a result for each entry. See the [batch] module for details.
+
+## Errors
+
+Failures reported by the Secret Service are classified before being returned.
+Every [PlatformFailure](keyring_core::Error::PlatformFailure) or
+[NoStorageAccess](keyring_core::Error::NoStorageAccess) error from this store
+wraps an [errors::Cause] (retrievable with [errors::Cause::of]) that says
+whether the session bus is missing, the keyring daemon isn't running,
+a prompt was dismissed, and so on. (Expired credentials and compare-and-set
+conflicts are also reported with a cause of their own: see [errors::is_expired]
+and [errors::is_conflict].) If an item is deleted out from under an
+operation on it, the operation fails with a
+[NoEntry](keyring_core::Error::NoEntry) error.
+
 ## Features
 
-This crate has no features of its own: all of its features are simply passed
+Most of this crate's features are simply passed
 on to the [secret-service crate](https://crates.io/crates/secret-service)
 that it uses to communicate with Secret Service. (See the
 [docs for that crate](https://docs.rs/docs/zbus-secret-service) for details.)
@@ -60,6 +176,28 @@ You must enable exactly one of the four (mutually-exclusive) features
 in order to declare which async runtime you are using and
 which cryptography utilities you want to use.
 
+The `fallback` feature enables a store configuration in which items are kept
+in a passphrase-encrypted file when the Secret Service can't be used.
+See [Store::new_with_configuration] and the [fallback] module for details.
+
+The `archive` feature enables [Store::export] and [Store::import], which move
+items between machines in a passphrase-encrypted archive.
+See the [archive] module for details.
+
+The `structured` feature enables [structured::StructuredCredential], which keeps
+several named fields (with a versioned layout) in the secret of a single item.
+
+The `metrics` feature enables [Store::metrics], which reports how many calls
+a store has made to the Secret Service, how long they took, and how many failed.
+The `tracing` feature wraps each of those calls in a
+[tracing](https://crates.io/crates/tracing) span. See the [metrics] module
+for details.
+
+The `cli` feature builds the `ss-keyring` command-line tool, which lists, reads,
+writes, and deletes items, and manages collections, from the shell. Install it with
+`cargo install zbus-secret-service-keyring-store --features cli,crypto-rust`
+and run `ss-keyring --help` for usage.
+
 ## Headless usage
 
 If you must use the secret-service on a headless linux box, be aware that there
@@ -85,6 +223,16 @@ function unlock-keyring ()
 For an excellent treatment of all the headless zbus issues, see
 [this answer on ServerFault](https://serverfault.com/a/906224/79617).
 
+## Diagnostics
+
+If the store doesn't work on some machine, [Store::diagnose] will report on
+whether the session bus is reachable, which program provides the Secret Service,
+and the state of its collections. The `diagnose` example program in this crate
+prints that report.
+
+If you can't get the Secret Service working on a headless box, consider
+enabling the `fallback` feature and configuring a fallback for your store.
+
 ## Usage on Windows Subsystem for Linux
 
 As noted in
@@ -104,10 +252,28 @@ passwords or secrets in this interface.
 
 */
 
+#[cfg(feature = "archive")]
+pub mod archive;
+pub mod batch;
+pub mod bulk;
 pub mod cred;
+#[cfg(any(feature = "archive", feature = "fallback"))]
+mod crypto;
+pub mod diagnostics;
 pub mod errors;
+#[cfg(feature = "fallback")]
+pub mod fallback;
+pub mod handle;
+pub mod history;
+pub mod metrics;
+pub mod migrate;
+pub mod profile;
+pub mod resolve;
 mod service;
 pub mod store;
+#[cfg(feature = "structured")]
+pub mod structured;
+pub mod uri;
 pub use store::Store;
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,252 @@
+/*!
+
+Secret Service call metrics and tracing.
+
+Each store connects to the Secret Service once, when it's created, and all
+of its entries reuse that connection (and the encrypted session opened on it).
+The calls the store makes over that connection can be instrumented, so that
+time spent waiting on the Secret Service can be told apart from time spent
+elsewhere.
+
+With the `metrics` feature, each store counts the calls it makes, the calls
+that fail, and the time they take, broken down by [Operation].
+[Store::metrics](crate::Store::metrics) returns a snapshot of these counts.
+Every call the store makes on its connection is counted, except for making
+the local proxies for items and collections (which doesn't call the service).
+The snapshot also counts the _prompt-capable calls_: those (unlocking items or
+collections, and creating collections) for which the service may prompt the
+user. The secret-service crate doesn't say whether a prompt was actually
+shown, so this is an upper bound on the number of prompts; the time taken by
+these calls includes the time the user spent answering any prompt.
+
+With the `tracing` feature, each of these calls is made inside a `DEBUG`-level
+[tracing](https://crates.io/crates/tracing) span named `secret_service`
+whose `operation` field is the [name](Operation::name) of the operation,
+and a failed call emits a `DEBUG`-level event with the error.
+
+Without either feature, the instrumentation compiles away to nothing.
+
+*/
+
+#[cfg(feature = "metrics")]
+use std::collections::BTreeMap;
+#[cfg(feature = "metrics")]
+use std::sync::Mutex;
+#[cfg(feature = "metrics")]
+use std::time::{Duration, Instant};
+
+use secret_service::Error as ServiceError;
+
+use crate::errors::decode_error;
+use keyring_core::Result;
+
+/// The kinds of call this store makes to the Secret Service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Operation {
+    /// Connecting to the service and opening an encrypted session.
+    Connect,
+    /// Searching for items.
+    Search,
+    /// Unlocking items or a collection.
+    Unlock,
+    /// Reading a secret (or its content type).
+    GetSecret,
+    /// Writing a secret.
+    SetSecret,
+    /// Creating an item.
+    CreateItem,
+    /// Deleting an item or a collection.
+    Delete,
+    /// Creating a collection.
+    CreateCollection,
+    /// Reading an item's attributes.
+    GetAttributes,
+    /// Writing an item's attributes.
+    SetAttributes,
+    /// Reading the label of an item or a collection.
+    GetLabel,
+    /// Writing an item's label.
+    SetLabel,
+    /// Reading another property (whether it's locked, or when it was created
+    /// or modified) of an item or a collection.
+    GetProperty,
+    /// Locking a collection.
+    Lock,
+    /// Looking up a collection by alias, or listing the collections.
+    FindCollection,
+}
+
+impl Operation {
+    /// The name used for this operation in traces.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::Connect => "connect",
+            Operation::Search => "search",
+            Operation::Unlock => "unlock",
+            Operation::GetSecret => "get-secret",
+            Operation::SetSecret => "set-secret",
+            Operation::CreateItem => "create-item",
+            Operation::Delete => "delete",
+            Operation::CreateCollection => "create-collection",
+            Operation::GetAttributes => "get-attributes",
+            Operation::SetAttributes => "set-attributes",
+            Operation::GetLabel => "get-label",
+            Operation::SetLabel => "set-label",
+            Operation::GetProperty => "get-property",
+            Operation::Lock => "lock",
+            Operation::FindCollection => "find-collection",
+        }
+    }
+
+    /// Whether the service may prompt the user during this operation.
+    #[cfg(feature = "metrics")]
+    fn may_prompt(&self) -> bool {
+        matches!(self, Operation::Unlock | Operation::CreateCollection)
+    }
+}
+
+/// The counts kept for one kind of operation.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OperationMetrics {
+    /// How many calls were made.
+    pub calls: u64,
+    /// How many of the calls failed.
+    pub errors: u64,
+    /// The total time taken by the calls.
+    pub total: Duration,
+    /// The time taken by the slowest call.
+    pub max: Duration,
+}
+
+#[cfg(feature = "metrics")]
+impl OperationMetrics {
+    /// The average time taken by a call (zero if there were none).
+    pub fn mean(&self) -> Duration {
+        match u32::try_from(self.calls) {
+            Ok(0) => Duration::ZERO,
+            Ok(n) => self.total / n,
+            Err(_) => Duration::from_secs_f64(self.total.as_secs_f64() / self.calls as f64),
+        }
+    }
+}
+
+/// A snapshot of the calls a store has made to the Secret Service.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// The counts for each kind of operation that has been called.
+    pub operations: BTreeMap<Operation, OperationMetrics>,
+    /// How many calls were of a kind that may prompt the user.
+    pub prompt_capable_calls: u64,
+}
+
+#[cfg(feature = "metrics")]
+impl Metrics {
+    /// The counts for one kind of operation (all zero if it hasn't been called).
+    pub fn get(&self, operation: Operation) -> OperationMetrics {
+        self.operations.get(&operation).cloned().unwrap_or_default()
+    }
+
+    /// The total number of calls made.
+    pub fn calls(&self) -> u64 {
+        self.operations.values().map(|m| m.calls).sum()
+    }
+
+    /// The total number of calls that failed.
+    pub fn errors(&self) -> u64 {
+        self.operations.values().map(|m| m.errors).sum()
+    }
+
+    /// The total time taken by all the calls.
+    pub fn total(&self) -> Duration {
+        self.operations.values().map(|m| m.total).sum()
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl std::fmt::Display for Metrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} calls ({} failed, {} could have prompted) in {:?}",
+            self.calls(),
+            self.errors(),
+            self.prompt_capable_calls,
+            self.total()
+        )?;
+        for (operation, m) in self.operations.iter() {
+            writeln!(
+                f,
+                "  {}: {} calls ({} failed), mean {:?}, max {:?}",
+                operation.name(),
+                m.calls,
+                m.errors,
+                m.mean(),
+                m.max
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Records the calls made by a store's connection to the Secret Service.
+#[derive(Debug, Default)]
+pub(crate) struct Recorder {
+    #[cfg(feature = "metrics")]
+    metrics: Mutex<Metrics>,
+}
+
+impl Recorder {
+    /// Make a call to the Secret Service, recording it as the given operation.
+    ///
+    /// Errors from the call are decoded as for any other call.
+    #[inline]
+    pub(crate) fn call<T>(
+        &self,
+        operation: Operation,
+        call: impl FnOnce() -> std::result::Result<T, ServiceError>,
+    ) -> Result<T> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("secret_service", operation = operation.name()).entered();
+        #[cfg(feature = "metrics")]
+        let start = Instant::now();
+        let result = call();
+        #[cfg(feature = "metrics")]
+        self.record(operation, start.elapsed(), result.is_err());
+        #[cfg(feature = "tracing")]
+        if let Err(err) = &result {
+            tracing::debug!(error = %err, "Secret Service call failed");
+        }
+        #[cfg(not(any(feature = "metrics", feature = "tracing")))]
+        let _ = operation;
+        result.map_err(decode_error)
+    }
+
+    /// A snapshot of the calls recorded so far.
+    #[cfg(feature = "metrics")]
+    pub(crate) fn snapshot(&self) -> Metrics {
+        self.metrics
+            .lock()
+            .expect("Mutex failure in credential store: please report a bug")
+            .clone()
+    }
+
+    #[cfg(feature = "metrics")]
+    fn record(&self, operation: Operation, elapsed: Duration, failed: bool) {
+        let mut metrics = self
+            .metrics
+            .lock()
+            .expect("Mutex failure in credential store: please report a bug");
+        if operation.may_prompt() {
+            metrics.prompt_capable_calls += 1;
+        }
+        let m = metrics.operations.entry(operation).or_default();
+        m.calls += 1;
+        if failed {
+            m.errors += 1;
+        }
+        m.total += elapsed;
+        m.max = m.max.max(elapsed);
+    }
+}
@@ -0,0 +1,209 @@
+/*!
+
+Migration of credentials from other keyring-core stores.
+
+The [migrate] function copies the entries found by a search of any keyring-core
+credential store into a Secret Service store. Each copied entry keeps its
+service and user, and those of its attributes that can be set on a Secret Service
+item: that is, all but the attributes controlled by this store and by the
+destination entry's [profile](crate::profile), and the `xdg:schema` attribute
+(which would change the profile that the copy is read with). (An attribute that the destination
+entry is identified by, via an `attribute:` modifier, is left alone if the original
+has the same value, and otherwise makes the copy fail.) After each copy is made,
+it is read back and compared with the original; only if the comparison succeeds
+(and the caller asked for it) is the original deleted. A copy that can't be
+completed is deleted, so that migrating again will retry it.
+
+A dry run reports what would be copied without changing either store.
+The report's `Display` output is a human-readable summary of the migration.
+
+*/
+
+use std::collections::{BTreeMap, HashMap};
+
+use keyring_core::api::CredentialStoreApi;
+use keyring_core::{Entry, Error, Result};
+
+use crate::Store;
+use crate::cred::{RESERVED_ATTRIBUTES, Specifier};
+use crate::profile::{Profile, SCHEMA_ATTRIBUTE};
+
+/// Options that control a migration.
+#[derive(Debug, Clone, Default)]
+pub struct MigrationOptions {
+    /// Report what would be done, but don't change either store.
+    pub dry_run: bool,
+    /// Delete each original after its copy has been verified.
+    pub delete_originals: bool,
+    /// Modifiers (e.g., `target`) used to build each destination entry.
+    pub modifiers: HashMap<String, String>,
+}
+
+/// What happened to a single source entry.
+#[derive(Debug)]
+pub enum Outcome {
+    /// (Dry run) The entry would be copied.
+    WouldCopy,
+    /// The entry was copied and the copy verified.
+    Copied,
+    /// The entry was copied, the copy verified, and the original deleted.
+    Moved,
+    /// The entry was not copied, for the given reason.
+    Skipped(String),
+    /// The copy (or the deletion of the original) failed.
+    Failed(Error),
+}
+
+/// The outcome for a single source entry.
+#[derive(Debug)]
+pub struct MigrationRecord {
+    pub service: String,
+    pub user: String,
+    pub outcome: Outcome,
+}
+
+/// The outcomes for all the source entries found.
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    pub records: Vec<MigrationRecord>,
+}
+
+impl MigrationReport {
+    /// Whether any entry failed to migrate.
+    pub fn has_failures(&self) -> bool {
+        self.records
+            .iter()
+            .any(|r| matches!(r.outcome, Outcome::Failed(_)))
+    }
+}
+
+impl std::fmt::Display for MigrationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for r in &self.records {
+            let what = format!("service: {}, user: {}", r.service, r.user);
+            match &r.outcome {
+                Outcome::WouldCopy => writeln!(f, "would copy {what}")?,
+                Outcome::Copied => writeln!(f, "copied {what}")?,
+                Outcome::Moved => writeln!(f, "moved {what}")?,
+                Outcome::Skipped(why) => writeln!(f, "skipped {what}: {why}")?,
+                Outcome::Failed(err) => writeln!(f, "failed {what}: {err}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Copy the entries found by searching `source` with `spec` into `dest`.
+///
+/// Errors in the source search are returned; errors on individual
+/// entries are recorded in the report and don't stop the migration.
+pub fn migrate(
+    source: &dyn CredentialStoreApi,
+    spec: &HashMap<&str, &str>,
+    dest: &Store,
+    options: &MigrationOptions,
+) -> Result<MigrationReport> {
+    let mut report = MigrationReport::default();
+    for entry in source.search(spec)? {
+        let Some((service, user)) = entry.get_specifiers() else {
+            report.records.push(MigrationRecord {
+                service: String::new(),
+                user: String::new(),
+                outcome: Outcome::Skipped("the entry has no service and user".to_string()),
+            });
+            continue;
+        };
+        let outcome =
+            migrate_entry(&entry, &service, &user, dest, options).unwrap_or_else(Outcome::Failed);
+        report.records.push(MigrationRecord {
+            service,
+            user,
+            outcome,
+        });
+    }
+    Ok(report)
+}
+
+fn migrate_entry(
+    entry: &Entry,
+    service: &str,
+    user: &str,
+    dest: &Store,
+    options: &MigrationOptions,
+) -> Result<Outcome> {
+    let modifiers: HashMap<&str, &str> = options
+        .modifiers
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    let copy = dest.build(service, user, Some(&modifiers))?;
+    match copy.get_secret() {
+        Ok(_) => {
+            return Ok(Outcome::Skipped(
+                "the destination already has this entry".to_string(),
+            ));
+        }
+        Err(Error::NoEntry) => {}
+        Err(Error::Ambiguous(_)) => {
+            return Ok(Outcome::Skipped(
+                "the destination has more than one matching item".to_string(),
+            ));
+        }
+        Err(err) => return Err(err),
+    }
+    let secret = entry.get_secret()?;
+    let all_attributes = entry.get_attributes()?;
+    let (profile, identifying) = match copy.as_any().downcast_ref::<Specifier>() {
+        Some(specifier) => (specifier.profile, specifier.attributes.clone()),
+        None => (Profile::Keyring, BTreeMap::new()),
+    };
+    let attributes: HashMap<&str, &str> = all_attributes
+        .iter()
+        .filter(|(k, v)| {
+            !RESERVED_ATTRIBUTES.contains(&k.as_str())
+                && k.as_str() != SCHEMA_ATTRIBUTE
+                && !profile.controls(k)
+                && identifying.get(*k) != Some(*v)
+        })
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    if options.dry_run {
+        return Ok(Outcome::WouldCopy);
+    }
+    copy.set_secret(&secret)?;
+    if let Err(err) = complete_copy(&copy, &secret, &attributes) {
+        // a rerun would skip a partial copy, so don't leave one behind
+        // (if it can't be deleted, the original error is still the one to report)
+        let _ = copy.delete_credential();
+        return Err(err);
+    }
+    if options.delete_originals {
+        entry.delete_credential()?;
+        return Ok(Outcome::Moved);
+    }
+    Ok(Outcome::Copied)
+}
+
+/// Copy the attributes onto a copy whose secret has been set, then verify it.
+fn complete_copy(copy: &Entry, secret: &[u8], attributes: &HashMap<&str, &str>) -> Result<()> {
+    if !attributes.is_empty() {
+        copy.update_attributes(attributes)?;
+    }
+    // verify the copy before touching the original
+    if copy.get_secret()? != secret {
+        return Err(Error::Invalid(
+            "secret".to_string(),
+            "the copy does not match the original".to_string(),
+        ));
+    }
+    let copied = copy.get_attributes()?;
+    for (k, v) in attributes.iter() {
+        if copied.get(*k).map(|c| c != v).unwrap_or(true) {
+            return Err(Error::Invalid(
+                k.to_string(),
+                "the copy does not match the original".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
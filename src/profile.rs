@@ -0,0 +1,172 @@
+/*!
+
+Item profiles.
+
+By default, the items for this store's entries keep the entry's service and
+user in their `service` and `username` attributes. Other applications lay
+out their items differently, so an entry can instead be built with a `profile`
+modifier that says how its items are laid out. Entries built with a profile
+read and write the same items as the applications that use that layout.
+
+The profiles are:
+
+- `keyring` (the default): the service and user are kept in the `service`
+  and `username` attributes, and new items are labeled `keyring:<user>@<service>`.
+- `network-password`: the layout of the `org.gnome.keyring.NetworkPassword` schema,
+  used by GNOME applications such as Evolution, GVfs, and NetworkManager for
+  the passwords of network resources. The service is the server, kept in
+  the `server` attribute, and the user is kept in the `user` attribute. The
+  schema's other attributes (`protocol`, `port`, `domain`, `object`, and `authtype`)
+  can be given as extra identifying attributes (with `attribute:` modifiers). New
+  items are labeled with a URL formed from the attributes, such as
+  `smb://WORKGROUP;alice@fileserver:445/share`.
+- `keytar`: the layout used by [keytar](https://github.com/atom/node-keytar)
+  (and so by Electron applications and VS Code's secret storage), which follows
+  the `org.freedesktop.Secret.Generic` schema. The service is kept in the
+  `service` attribute, the user is kept in the `account` attribute, and new items
+  are labeled `<service>/<user>`.
+
+The profiles other than `keyring` mark their items with the `xdg:schema`
+attribute that names their schema (as libsecret does), search only for items
+so marked, and give secrets the `text/plain` content type (since libsecret only
+reads passwords with that type). An entry's profile can't be changed through it,
+and the attributes a profile controls can't be updated through the entry.
+
+Profiles other than `keyring` are not supported by the fallback store.
+
+*/
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::service::DEFAULT_CONTENT_TYPE;
+
+/// The attribute that libsecret uses to record an item's schema.
+pub const SCHEMA_ATTRIBUTE: &str = "xdg:schema";
+
+/// The schema of GNOME network passwords.
+pub const NETWORK_PASSWORD_SCHEMA: &str = "org.gnome.keyring.NetworkPassword";
+
+/// The schema of the items kept by keytar.
+pub const GENERIC_SCHEMA: &str = "org.freedesktop.Secret.Generic";
+
+/// The layout of an entry's items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Profile {
+    /// This crate's own layout.
+    #[default]
+    Keyring,
+    /// The layout of GNOME network passwords.
+    NetworkPassword,
+    /// The layout of keytar's items.
+    Keytar,
+}
+
+impl Profile {
+    /// All the profiles.
+    pub const ALL: [Profile; 3] = [Profile::Keyring, Profile::NetworkPassword, Profile::Keytar];
+
+    /// The profile with the given name, if there is one.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.name() == name)
+    }
+
+    /// The name of this profile, as given in a `profile` modifier.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Profile::Keyring => "keyring",
+            Profile::NetworkPassword => "network-password",
+            Profile::Keytar => "keytar",
+        }
+    }
+
+    /// The profile of an item with the given attributes.
+    pub(crate) fn of_item(attributes: &HashMap<String, String>) -> Self {
+        let schema = attributes.get(SCHEMA_ATTRIBUTE).map(String::as_str);
+        Self::ALL
+            .into_iter()
+            .find(|p| p.schema().is_some() && p.schema() == schema)
+            .unwrap_or_default()
+    }
+
+    /// The attribute that holds an entry's service.
+    pub fn service_attribute(&self) -> &'static str {
+        match self {
+            Profile::Keyring | Profile::Keytar => "service",
+            Profile::NetworkPassword => "server",
+        }
+    }
+
+    /// The attribute that holds an entry's user.
+    pub fn user_attribute(&self) -> &'static str {
+        match self {
+            Profile::Keyring => "username",
+            Profile::NetworkPassword => "user",
+            Profile::Keytar => "account",
+        }
+    }
+
+    /// The schema that items with this profile are marked with, if any.
+    pub fn schema(&self) -> Option<&'static str> {
+        match self {
+            Profile::Keyring => None,
+            Profile::NetworkPassword => Some(NETWORK_PASSWORD_SCHEMA),
+            Profile::Keytar => Some(GENERIC_SCHEMA),
+        }
+    }
+
+    /// The attributes of this profile's schema other than those it controls.
+    pub fn schema_attributes(&self) -> &'static [&'static str] {
+        match self {
+            Profile::Keyring | Profile::Keytar => &[],
+            Profile::NetworkPassword => &["protocol", "port", "domain", "object", "authtype"],
+        }
+    }
+
+    /// Whether the given attribute is controlled by this profile.
+    pub(crate) fn controls(&self, attribute: &str) -> bool {
+        attribute == self.service_attribute()
+            || attribute == self.user_attribute()
+            || (self.schema().is_some() && attribute == SCHEMA_ATTRIBUTE)
+    }
+
+    /// The content type given to secrets.
+    pub(crate) fn content_type(&self) -> &'static str {
+        match self {
+            Profile::Keyring => DEFAULT_CONTENT_TYPE,
+            Profile::NetworkPassword | Profile::Keytar => "text/plain",
+        }
+    }
+
+    /// The label given to new items when no `label` modifier is specified.
+    pub(crate) fn default_label(
+        &self,
+        service: &str,
+        user: &str,
+        attributes: &BTreeMap<String, String>,
+    ) -> String {
+        match self {
+            Profile::Keyring => format!("keyring:{user}@{service}"),
+            Profile::NetworkPassword => {
+                let mut label = String::new();
+                if let Some(protocol) = attributes.get("protocol") {
+                    label.push_str(&format!("{protocol}://"));
+                }
+                if let Some(domain) = attributes.get("domain") {
+                    label.push_str(&format!("{domain};"));
+                }
+                if !user.is_empty() {
+                    label.push_str(&format!("{user}@"));
+                }
+                label.push_str(service);
+                if let Some(port) = attributes.get("port") {
+                    label.push_str(&format!(":{port}"));
+                }
+                if let Some(object) = attributes.get("object") {
+                    label.push_str(&format!("/{}", object.trim_start_matches('/')));
+                }
+                label
+            }
+            Profile::Keytar => format!("{service}/{user}"),
+        }
+    }
+}
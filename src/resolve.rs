@@ -0,0 +1,161 @@
+/*!
+
+Resolving secret references in text.
+
+Configuration files can refer to credentials, rather than contain their
+secrets, with placeholders of the form `${secret-service:<service>/<user>}` or
+`${secret-service:<target>/<service>/<user>}`. The target, service, and user
+are percent-encoded as in a [credential URI](crate::uri), so they can contain
+any characters (including `/` and `}`).
+
+[Store::resolve_secrets](crate::Store::resolve_secrets) replaces each placeholder
+in a text with the (UTF-8) secret of the entry that the store
+[builds](keyring_core::api::CredentialStoreApi::build) for it. The secrets are
+substituted as is, with no quoting, so it's up to the text's author to
+put each placeholder where its secret will be read correctly.
+
+The placeholders are all found before any secret is read, and each distinct
+entry is read just once, no matter how many placeholders refer to it. The
+entries of placeholders without a target are read together, as by
+[Store::get_secrets](crate::Store::get_secrets), so there is one search per
+entry, at most one unlock for all of them, and one fetch per secret; those with
+a target are read one at a time. (With the store's fallback, every entry is
+read one at a time.) If any
+placeholder is malformed, or any entry can't be read, the whole resolution fails,
+except that entries with no credential are handled as the [ResolveMode] says.
+Text that isn't part of a placeholder (including other `${...}` expressions)
+is left alone.
+
+*/
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use keyring_core::api::CredentialStoreApi;
+use keyring_core::{Error, Result};
+
+use crate::Store;
+use crate::uri::decode;
+
+/// What to do with a placeholder whose entry has no credential.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResolveMode {
+    /// Fail the resolution.
+    Strict,
+    /// Leave the placeholder in the text.
+    Lenient,
+}
+
+/// The start of a placeholder.
+pub const PLACEHOLDER_PREFIX: &str = "${secret-service:";
+
+/// A placeholder found in a text.
+struct Placeholder {
+    /// Where the placeholder is in the text.
+    range: Range<usize>,
+    /// The target, service, and user named by the placeholder.
+    name: (Option<String>, String, String),
+}
+
+/// Replace the placeholders in a text with the secrets they refer to.
+pub(crate) fn resolve(store: &Store, text: &str, mode: ResolveMode) -> Result<String> {
+    let placeholders = find_placeholders(text)?;
+    let mut firsts: Vec<&Placeholder> = Vec::new();
+    for placeholder in placeholders.iter() {
+        if !firsts.iter().any(|p| p.name == placeholder.name) {
+            firsts.push(placeholder);
+        }
+    }
+    let (plain, targeted): (Vec<&Placeholder>, Vec<&Placeholder>) = firsts
+        .into_iter()
+        .partition(|p| p.name.0.is_none() && !store.is_fallback());
+    let mut secrets: HashMap<&(Option<String>, String, String), Option<String>> = HashMap::new();
+    if !plain.is_empty() {
+        let names: Vec<(&str, &str)> = plain
+            .iter()
+            .map(|p| (p.name.1.as_str(), p.name.2.as_str()))
+            .collect();
+        let results = crate::batch::get_secrets(store.service()?, &names)?;
+        for (placeholder, result) in plain.into_iter().zip(results) {
+            let result = result
+                .and_then(|s| String::from_utf8(s).map_err(|e| Error::BadEncoding(e.into_bytes())));
+            let secret = found(text, placeholder, result, mode)?;
+            secrets.insert(&placeholder.name, secret);
+        }
+    }
+    for placeholder in targeted {
+        let (target, service, user) = &placeholder.name;
+        let modifiers: HashMap<&str, &str> =
+            target.iter().map(|t| ("target", t.as_str())).collect();
+        let entry = store.build(service, user, Some(&modifiers))?;
+        let secret = found(text, placeholder, entry.get_password(), mode)?;
+        secrets.insert(&placeholder.name, secret);
+    }
+    let mut result = String::with_capacity(text.len());
+    let mut copied = 0;
+    for placeholder in placeholders.iter() {
+        if let Some(secret) = &secrets[&placeholder.name] {
+            result.push_str(&text[copied..placeholder.range.start]);
+            result.push_str(secret);
+            copied = placeholder.range.end;
+        }
+    }
+    result.push_str(&text[copied..]);
+    Ok(result)
+}
+
+/// Handle the result of reading a placeholder's entry as the mode says.
+///
+/// Returns the secret, or `None` if the placeholder is to be left in the text.
+fn found(
+    text: &str,
+    placeholder: &Placeholder,
+    result: Result<String>,
+    mode: ResolveMode,
+) -> Result<Option<String>> {
+    match result {
+        Ok(secret) => Ok(Some(secret)),
+        Err(Error::NoEntry) if mode == ResolveMode::Lenient => Ok(None),
+        Err(Error::NoEntry) => Err(Error::Invalid(
+            "placeholder".to_string(),
+            format!("{} names no credential", &text[placeholder.range.clone()]),
+        )),
+        Err(err) => Err(err),
+    }
+}
+
+/// Find all the placeholders in a text, in order.
+fn find_placeholders(text: &str) -> Result<Vec<Placeholder>> {
+    let mut result = Vec::new();
+    let mut searched = 0;
+    while let Some(offset) = text[searched..].find(PLACEHOLDER_PREFIX) {
+        let start = searched + offset;
+        let body_start = start + PLACEHOLDER_PREFIX.len();
+        let invalid = |why: &str| {
+            let shown: String = text[start..].chars().take(60).collect();
+            Error::Invalid("placeholder".to_string(), format!("{shown} {why}"))
+        };
+        let body_len = text[body_start..]
+            .find('}')
+            .ok_or_else(|| invalid("is not terminated by '}'"))?;
+        let end = body_start + body_len + 1;
+        let parts = text[body_start..end - 1]
+            .split('/')
+            .map(|part| decode(part).ok_or_else(|| invalid("has a bad percent-encoding")))
+            .collect::<Result<Vec<String>>>()?;
+        let name = match <[String; 3]>::try_from(parts) {
+            Ok([target, service, user]) if target.is_empty() => (None, service, user),
+            Ok([target, service, user]) => (Some(target), service, user),
+            Err(parts) => match <[String; 2]>::try_from(parts) {
+                Ok([service, user]) => (None, service, user),
+                Err(_) => return Err(invalid("is not of the form [<target>/]<service>/<user>")),
+            },
+        };
+        result.push(Placeholder {
+            range: start..end,
+            name,
+        });
+        searched = end;
+    }
+    Ok(result)
+}
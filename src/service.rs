@@ -8,131 +8,566 @@ to the Secret Service. Each store holds the singleton used by its creds.
 */
 
 use std::collections::HashMap;
-use std::sync::Mutex;
+use std::sync::{Mutex, MutexGuard};
 
 use secret_service::{EncryptionType, blocking::Item, blocking::SecretService};
 use zbus::zvariant::OwnedObjectPath;
 
-use crate::errors::{decode_error, platform_failure};
+use crate::diagnostics::CollectionStatus;
+use crate::errors::decode_error;
+use crate::history;
+use crate::metrics::{Operation, Recorder};
 use keyring_core::{Error, Result};
 
+pub(crate) use util::{ALIAS_PREFIX, collection_path_of, describe_collections};
+
+/// The content type given to secrets unless the caller says otherwise.
+pub(crate) const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// An item's attributes and secret, as read by a batch.
+pub(crate) type AttributesAndSecret = (HashMap<String, String>, Vec<u8>);
+
 pub(crate) struct Service<'a> {
     ss: Mutex<SecretService<'a>>,
+    recorder: Recorder,
 }
 
-impl Service<'_> {
+impl<'a> Service<'a> {
     pub(crate) fn new() -> Result<Self> {
+        let recorder = Recorder::default();
+        let ss = recorder.call(Operation::Connect, || {
+            SecretService::connect(EncryptionType::Dh)
+        })?;
         Ok(Self {
-            ss: Mutex::new(SecretService::connect(EncryptionType::Dh).map_err(platform_failure)?),
+            ss: Mutex::new(ss),
+            recorder,
         })
     }
 
-    pub(crate) fn find_matching_items(
+    /// A snapshot of the calls made to the service so far.
+    #[cfg(feature = "metrics")]
+    pub(crate) fn metrics(&self) -> crate::metrics::Metrics {
+        self.recorder.snapshot()
+    }
+
+    /// Take exclusive access to the service, so that a sequence of calls
+    /// can be made without any other operation through this service
+    /// intervening. Don't call the service's own methods while holding it.
+    pub(crate) fn exclusive(&self) -> Exclusive<'_, 'a> {
+        Exclusive {
+            ss: self
+                .ss
+                .lock()
+                .expect("Mutex failure in credential store: please report a bug"),
+            recorder: &self.recorder,
+        }
+    }
+
+    /// Whether the service has a collection with the `default` alias.
+    #[cfg(feature = "fallback")]
+    pub(crate) fn has_default_collection(&self) -> bool {
+        let ex = self.exclusive();
+        ex.recorder
+            .call(Operation::FindCollection, || ex.ss.get_default_collection())
+            .is_ok()
+    }
+
+    /// Search for matching items without unlocking any of them.
+    ///
+    /// Returns the paths of the unlocked and the locked items, in that order.
+    pub(crate) fn find_matching_items_by_lock(
         &self,
         attributes: &HashMap<&str, &str>,
-    ) -> Result<Vec<OwnedObjectPath>> {
-        let ss = self
-            .ss
-            .lock()
-            .expect("Mutex failure in credential store: please report a bug");
-        let search = ss.search_items(attributes.clone()).map_err(decode_error)?;
-        if !search.locked.is_empty() {
-            let item_refs: Vec<&Item> = search.locked.iter().collect();
-            ss.unlock_all(item_refs.as_slice()).map_err(decode_error)?;
-        }
-        let results = search
+    ) -> Result<(Vec<OwnedObjectPath>, Vec<OwnedObjectPath>)> {
+        let ex = self.exclusive();
+        let search = ex
+            .recorder
+            .call(Operation::Search, || ex.ss.search_items(attributes.clone()))?;
+        let unlocked = search
             .unlocked
             .iter()
-            .chain(search.locked.iter())
             .map(|i| i.item_path.clone())
             .collect();
+        let locked = search.locked.iter().map(|i| i.item_path.clone()).collect();
+        Ok((unlocked, locked))
+    }
+
+    /// Search for the items matching each of several attribute sets.
+    ///
+    /// All the locked items found are unlocked together, after all the searches.
+    pub(crate) fn find_matching_items_batch(
+        &self,
+        specs: &[HashMap<&str, &str>],
+    ) -> Result<Vec<Vec<OwnedObjectPath>>> {
+        let ex = self.exclusive();
+        let mut results = Vec::with_capacity(specs.len());
+        let mut locked = Vec::new();
+        for spec in specs {
+            let search = ex
+                .recorder
+                .call(Operation::Search, || ex.ss.search_items(spec.clone()))?;
+            results.push(
+                search
+                    .unlocked
+                    .iter()
+                    .chain(search.locked.iter())
+                    .map(|i| i.item_path.clone())
+                    .collect(),
+            );
+            locked.extend(search.locked);
+        }
+        if !locked.is_empty() {
+            let item_refs: Vec<&Item> = locked.iter().collect();
+            ex.recorder
+                .call(Operation::Unlock, || ex.ss.unlock_all(item_refs.as_slice()))?;
+        }
         Ok(results)
     }
 
+    /// Given the paths of existing items, retrieve their attributes and secrets.
+    pub(crate) fn get_secrets_batch(
+        &self,
+        paths: &[OwnedObjectPath],
+    ) -> Vec<Result<AttributesAndSecret>> {
+        let ex = self.exclusive();
+        paths
+            .iter()
+            .map(|path| {
+                let item = ex.ss.get_item_by_path(path.clone()).map_err(decode_error)?;
+                let attributes = ex
+                    .recorder
+                    .call(Operation::GetAttributes, || item.get_attributes())?;
+                let secret = ex
+                    .recorder
+                    .call(Operation::GetSecret, || item.get_secret())?;
+                Ok((attributes, secret))
+            })
+            .collect()
+    }
+
+    /// Given the paths of existing items, set their secrets.
+    pub(crate) fn set_secrets_batch(
+        &self,
+        updates: &[(&OwnedObjectPath, &[u8])],
+        content_type: &str,
+    ) -> Vec<Result<()>> {
+        let ex = self.exclusive();
+        updates
+            .iter()
+            .map(|(path, secret)| {
+                let item = ex
+                    .ss
+                    .get_item_by_path((*path).clone())
+                    .map_err(decode_error)?;
+                ex.recorder.call(Operation::SetSecret, || {
+                    item.set_secret(secret, content_type)
+                })
+            })
+            .collect()
+    }
+
+    /// Search a single collection (rather than the whole service) for matching items.
+    pub(crate) fn find_matching_items_in(
+        &self,
+        collection: &str,
+        attributes: &HashMap<&str, &str>,
+    ) -> Result<Vec<OwnedObjectPath>> {
+        let ex = self.exclusive();
+        let collection = util::get_collection(&ex.ss, ex.recorder, collection)?;
+        let items = ex.recorder.call(Operation::Search, || {
+            collection.search_items(attributes.clone())
+        })?;
+        Ok(items.iter().map(|i| i.item_path.clone()).collect())
+    }
+
+    /// Create an item in the named collection, creating the collection if necessary.
+    ///
+    /// If `replace` is true, an existing item in the collection with
+    /// the same attributes is replaced.
     pub(crate) fn create_item(
         &self,
         collection: &str,
         label: &str,
         attributes: HashMap<&str, &str>,
         secret: &[u8],
-    ) -> Result<()> {
-        let ss = self
-            .ss
-            .lock()
-            .expect("Mutex failure in credential store: please report a bug");
-        let collection = match util::get_collection(&ss, collection) {
+        content_type: &str,
+        replace: bool,
+    ) -> Result<OwnedObjectPath> {
+        let ex = self.exclusive();
+        let collection = match util::get_collection(&ex.ss, ex.recorder, collection) {
             Ok(c) => c,
-            Err(Error::NoEntry) => util::create_collection(&ss, collection)?,
+            Err(Error::NoEntry) => util::create_collection(&ex.ss, ex.recorder, collection)?,
             Err(e) => return Err(e),
         };
-        collection
-            .create_item(
-                label,
-                attributes,
-                secret,
-                true, // replace
-                "application/octet-stream",
-            )
-            .map_err(platform_failure)?;
-        Ok(())
+        let item = ex.recorder.call(Operation::CreateItem, || {
+            collection.create_item(label, attributes, secret, replace, content_type)
+        })?;
+        Ok(item.item_path.clone())
     }
 
-    pub(crate) fn delete_collection(&self, collection: &str) -> Result<()> {
-        let ss = self
-            .ss
-            .lock()
-            .expect("Mutex failure in credential store: please report a bug");
-        if collection.eq("default") {
-            return Err(Error::NotSupportedByStore(
-                "You cannot delete the default collection".to_string(),
+    /// Move an existing item into the named collection, creating the collection if necessary.
+    ///
+    /// The moved item's `target` attribute is set to the collection name.
+    /// The item is copied, the copy is checked against the original, and
+    /// only then is the original deleted. Returns the path of the moved item.
+    pub(crate) fn move_item(
+        &self,
+        path: &OwnedObjectPath,
+        collection: &str,
+    ) -> Result<OwnedObjectPath> {
+        let ex = self.exclusive();
+        let item = ex.ss.get_item_by_path(path.clone()).map_err(decode_error)?;
+        if ex
+            .recorder
+            .call(Operation::GetProperty, || item.is_locked())?
+        {
+            ex.recorder.call(Operation::Unlock, || item.unlock())?;
+        }
+        let label = ex.recorder.call(Operation::GetLabel, || item.get_label())?;
+        let mut attributes = ex
+            .recorder
+            .call(Operation::GetAttributes, || item.get_attributes())?;
+        attributes.insert("target".to_string(), collection.to_string());
+        let attributes: HashMap<&str, &str> = attributes
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        let content_type = ex
+            .recorder
+            .call(Operation::GetSecret, || item.get_secret_content_type())?;
+        let secret = ex
+            .recorder
+            .call(Operation::GetSecret, || item.get_secret())?;
+        let destination = match util::get_collection(&ex.ss, ex.recorder, collection) {
+            Ok(c) => c,
+            Err(Error::NoEntry) => util::create_collection(&ex.ss, ex.recorder, collection)?,
+            Err(e) => return Err(e),
+        };
+        if destination.collection_path == util::collection_path_of(path)? {
+            ex.recorder
+                .call(Operation::SetAttributes, || item.set_attributes(attributes))?;
+            return Ok(path.clone());
+        }
+        let existing = ex.recorder.call(Operation::Search, || {
+            destination.search_items(attributes.clone())
+        })?;
+        if !existing.is_empty() {
+            return Err(Error::Invalid(
+                "collection".to_string(),
+                format!("{collection} already has an item with the same attributes"),
+            ));
+        }
+        let copy = ex.recorder.call(Operation::CreateItem, || {
+            destination.create_item(&label, attributes.clone(), &secret, false, &content_type)
+        })?;
+        let copied_attributes = ex
+            .recorder
+            .call(Operation::GetAttributes, || copy.get_attributes())?;
+        let verified = ex.recorder.call(Operation::GetLabel, || copy.get_label())? == label
+            && attributes
+                .iter()
+                .all(|(k, v)| copied_attributes.get(*k).map(String::as_str) == Some(*v))
+            && ex
+                .recorder
+                .call(Operation::GetSecret, || copy.get_secret())?
+                == secret;
+        if !verified {
+            ex.recorder.call(Operation::Delete, || copy.delete())?;
+            return Err(Error::PlatformFailure(
+                format!("The copy of the item in {collection} doesn't match the original").into(),
             ));
         }
-        match util::get_collection(&ss, collection) {
-            Ok(c) => c.delete().map_err(decode_error),
+        ex.recorder.call(Operation::Delete, || item.delete())?;
+        Ok(copy.item_path.clone())
+    }
+
+    pub(crate) fn delete_collection(&self, collection: &str) -> Result<()> {
+        let ex = self.exclusive();
+        match util::alias_of(collection) {
+            Some("default") => {
+                return Err(Error::NotSupportedByStore(
+                    "You cannot delete the default collection".to_string(),
+                ));
+            }
+            Some("session") => {
+                return Err(Error::NotSupportedByStore(
+                    "You cannot delete the session collection".to_string(),
+                ));
+            }
+            _ => {}
+        }
+        match util::get_collection(&ex.ss, ex.recorder, collection) {
+            Ok(c) => ex.recorder.call(Operation::Delete, || c.delete()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Describe all the collections in the service.
+    pub(crate) fn collections(&self) -> Result<Vec<CollectionStatus>> {
+        let ex = self.exclusive();
+        util::describe_collections(&ex.ss, ex.recorder)
+    }
+
+    /// Given an item's path, describe the collection it's in.
+    pub(crate) fn get_collection_status(&self, path: &OwnedObjectPath) -> Result<CollectionStatus> {
+        let ex = self.exclusive();
+        let collection_path = util::collection_path_of(path)?.to_string();
+        util::describe_collections(&ex.ss, ex.recorder)?
+            .into_iter()
+            .find(|c| c.path == collection_path)
+            .ok_or(Error::NoEntry)
+    }
+
+    /// Create the named collection, unless it already exists.
+    pub(crate) fn create_collection(&self, collection: &str) -> Result<()> {
+        let ex = self.exclusive();
+        match util::find_collection(&ex.ss, ex.recorder, collection) {
+            Ok(_) => Ok(()),
+            Err(Error::NoEntry) => {
+                util::create_collection(&ex.ss, ex.recorder, collection).map(|_| ())
+            }
             Err(e) => Err(e),
         }
     }
 
+    /// Lock or unlock the named collection.
+    pub(crate) fn set_collection_locked(&self, collection: &str, locked: bool) -> Result<()> {
+        let ex = self.exclusive();
+        let collection = util::find_collection(&ex.ss, ex.recorder, collection)?;
+        if locked {
+            ex.recorder.call(Operation::Lock, || collection.lock())
+        } else {
+            ex.recorder.call(Operation::Unlock, || collection.unlock())
+        }
+    }
+
     /// Given an item's path, ensure it exists and is unlocked
     pub(crate) fn ensure_unlocked(&self, path: &OwnedObjectPath) -> Result<()> {
-        let ss = self
+        self.exclusive().ensure_unlocked(path)
+    }
+
+    /// Given an existing item's path, set its secret if its current secret is the expected one.
+    ///
+    /// Returns whether the secret was set. The mutex is held throughout,
+    /// so no other operation through this service can intervene; a change
+    /// by another client is detected if it changes the item's `Modified`
+    /// property between the read and the check (see [Specifier::compare_and_set]).
+    /// If `keep` is given, the current secret is archived (keeping that many
+    /// versions, see [history]) once the check has succeeded.
+    ///
+    /// [Specifier::compare_and_set]: crate::cred::Specifier::compare_and_set
+    pub(crate) fn compare_and_set_secret(
+        &self,
+        path: &OwnedObjectPath,
+        expected: &[u8],
+        secret: &[u8],
+        content_type: &str,
+        keep: Option<usize>,
+    ) -> Result<bool> {
+        let ex = self.exclusive();
+        let item = ex.ss.get_item_by_path(path.clone()).map_err(decode_error)?;
+        let modified = ex
+            .recorder
+            .call(Operation::GetProperty, || item.get_modified())?;
+        if ex
+            .recorder
+            .call(Operation::GetSecret, || item.get_secret())?
+            != expected
+        {
+            return Ok(false);
+        }
+        if ex
+            .recorder
+            .call(Operation::GetProperty, || item.get_modified())?
+            != modified
+        {
+            return Ok(false);
+        }
+        if let Some(keep) = keep {
+            history::archive(&ex, path, secret, keep)?;
+        }
+        ex.recorder.call(Operation::SetSecret, || {
+            item.set_secret(secret, content_type)
+        })?;
+        Ok(true)
+    }
+
+    /// Given an existing item's path, retrieve the content type of its secret.
+    #[cfg(feature = "archive")]
+    pub(crate) fn get_content_type(&self, path: &OwnedObjectPath) -> Result<String> {
+        self.exclusive().get_content_type(path)
+    }
+
+    /// Given an existing item's path, name the collection it's in.
+    ///
+    /// The name is one that can be used as a target: `default` or `alias:session`
+    /// if the collection has one of those aliases, otherwise its label.
+    #[cfg(feature = "archive")]
+    pub(crate) fn get_collection_name(&self, path: &OwnedObjectPath) -> Result<String> {
+        let ex = self.exclusive();
+        let collection_path = util::collection_path_of(path)?;
+        for (alias, name) in [("default", "default"), ("session", "alias:session")] {
+            if let Ok(Some(c)) = util::collection_by_alias(&ex.ss, ex.recorder, alias)
+                && c.collection_path == collection_path
+            {
+                return Ok(name.to_string());
+            }
+        }
+        let collection = ex
+            .ss
+            .get_collection_by_path(collection_path)
+            .map_err(decode_error)?;
+        ex.recorder
+            .call(Operation::GetLabel, || collection.get_label())
+    }
+
+    /// Given an existing item's path, retrieve when it was created.
+    pub(crate) fn get_created(&self, path: &OwnedObjectPath) -> Result<u64> {
+        let ex = self.exclusive();
+        let item = ex.ss.get_item_by_path(path.clone()).map_err(decode_error)?;
+        ex.recorder
+            .call(Operation::GetProperty, || item.get_created())
+    }
+
+    pub(crate) fn find_matching_items(
+        &self,
+        attributes: &HashMap<&str, &str>,
+    ) -> Result<Vec<OwnedObjectPath>> {
+        self.exclusive().find_matching_items(attributes)
+    }
+
+    /// Given an item's path, set its secret.
+    pub(crate) fn set_secret(
+        &self,
+        path: &OwnedObjectPath,
+        secret: &[u8],
+        content_type: &str,
+    ) -> Result<()> {
+        self.exclusive().set_secret(path, secret, content_type)
+    }
+
+    /// Given an existing item's path, retrieve its secret.
+    pub(crate) fn get_secret(&self, path: &OwnedObjectPath) -> Result<Vec<u8>> {
+        self.exclusive().get_secret(path)
+    }
+
+    /// Given an existing item's path, retrieve its attributes.
+    pub(crate) fn get_attributes(&self, path: &OwnedObjectPath) -> Result<HashMap<String, String>> {
+        self.exclusive().get_attributes(path)
+    }
+
+    /// Given an existing item's path, update its attributes.
+    pub(crate) fn update_attributes(
+        &self,
+        path: &OwnedObjectPath,
+        attributes: &HashMap<&str, &str>,
+    ) -> Result<()> {
+        self.exclusive().update_attributes(path, attributes)
+    }
+
+    // Given an existing item's path, delete it.
+    pub(crate) fn delete(&self, path: &OwnedObjectPath) -> Result<()> {
+        self.exclusive().delete(path)
+    }
+
+    // Given an existing item's path, return its label.
+    pub(crate) fn get_label(&self, path: &OwnedObjectPath) -> Result<String> {
+        self.exclusive().get_label(path)
+    }
+
+    // Given an existing item's path, set its label.
+    pub(crate) fn set_label(&self, path: &OwnedObjectPath, label: &str) -> Result<()> {
+        self.exclusive().set_label(path, label)
+    }
+}
+
+/// Exclusive access to the service, obtained from [Service::exclusive].
+///
+/// Its item operations are those of the service, but they are all made
+/// while holding the service's mutex.
+pub(crate) struct Exclusive<'s, 'a> {
+    ss: MutexGuard<'s, SecretService<'a>>,
+    recorder: &'s Recorder,
+}
+
+impl Exclusive<'_, '_> {
+    pub(crate) fn find_matching_items(
+        &self,
+        attributes: &HashMap<&str, &str>,
+    ) -> Result<Vec<OwnedObjectPath>> {
+        let search = self.recorder.call(Operation::Search, || {
+            self.ss.search_items(attributes.clone())
+        })?;
+        if !search.locked.is_empty() {
+            let item_refs: Vec<&Item> = search.locked.iter().collect();
+            self.recorder.call(Operation::Unlock, || {
+                self.ss.unlock_all(item_refs.as_slice())
+            })?;
+        }
+        let results = search
+            .unlocked
+            .iter()
+            .chain(search.locked.iter())
+            .map(|i| i.item_path.clone())
+            .collect();
+        Ok(results)
+    }
+
+    /// Create an item in the same collection as an existing item.
+    pub(crate) fn create_sibling_item(
+        &self,
+        path: &OwnedObjectPath,
+        label: &str,
+        attributes: HashMap<&str, &str>,
+        secret: &[u8],
+    ) -> Result<OwnedObjectPath> {
+        let collection = self
             .ss
-            .lock()
-            .expect("Mutex failure in credential store: please report a bug");
-        let item = ss.get_item_by_path(path.clone()).map_err(decode_error)?;
-        item.ensure_unlocked().map_err(decode_error)
+            .get_collection_by_path(util::collection_path_of(path)?)
+            .map_err(decode_error)?;
+        let item = self.recorder.call(Operation::CreateItem, || {
+            collection.create_item(label, attributes, secret, false, DEFAULT_CONTENT_TYPE)
+        })?;
+        Ok(item.item_path.clone())
     }
 
     /// Given an item's path, set its secret.
-    pub(crate) fn set_secret(&self, path: &OwnedObjectPath, secret: &[u8]) -> Result<()> {
-        let ss = self
+    pub(crate) fn set_secret(
+        &self,
+        path: &OwnedObjectPath,
+        secret: &[u8],
+        content_type: &str,
+    ) -> Result<()> {
+        let item = self
             .ss
-            .lock()
-            .expect("Mutex failure in credential store: please report a bug");
-        let item = ss.get_item_by_path(path.clone()).map_err(decode_error)?;
-        item.set_secret(secret, "application/octet-stream")
-            .map_err(decode_error)
+            .get_item_by_path(path.clone())
+            .map_err(decode_error)?;
+        self.recorder.call(Operation::SetSecret, || {
+            item.set_secret(secret, content_type)
+        })
     }
 
     /// Given an existing item's path, retrieve its secret.
     pub(crate) fn get_secret(&self, path: &OwnedObjectPath) -> Result<Vec<u8>> {
-        let ss = self
+        let item = self
             .ss
-            .lock()
-            .expect("Mutex failure in credential store: please report a bug");
-        let item = ss.get_item_by_path(path.clone()).map_err(decode_error)?;
-        let secret = item.get_secret().map_err(decode_error)?;
+            .get_item_by_path(path.clone())
+            .map_err(decode_error)?;
+        let secret = self
+            .recorder
+            .call(Operation::GetSecret, || item.get_secret())?;
         Ok(secret)
     }
 
     /// Given an existing item's path, retrieve its attributes.
     pub(crate) fn get_attributes(&self, path: &OwnedObjectPath) -> Result<HashMap<String, String>> {
-        let ss = self
+        let item = self
             .ss
-            .lock()
-            .expect("Mutex failure in credential store: please report a bug");
-        let item = ss.get_item_by_path(path.clone()).map_err(decode_error)?;
-        let attributes = item.get_attributes().map_err(decode_error)?;
+            .get_item_by_path(path.clone())
+            .map_err(decode_error)?;
+        let attributes = self
+            .recorder
+            .call(Operation::GetAttributes, || item.get_attributes())?;
         Ok(attributes)
     }
 
@@ -142,12 +577,13 @@ impl Service<'_> {
         path: &OwnedObjectPath,
         attributes: &HashMap<&str, &str>,
     ) -> Result<()> {
-        let ss = self
+        let item = self
             .ss
-            .lock()
-            .expect("Mutex failure in credential store: please report a bug");
-        let item = ss.get_item_by_path(path.clone()).map_err(decode_error)?;
-        let existing = item.get_attributes().map_err(decode_error)?;
+            .get_item_by_path(path.clone())
+            .map_err(decode_error)?;
+        let existing = self
+            .recorder
+            .call(Operation::GetAttributes, || item.get_attributes())?;
         let mut updated: HashMap<&str, &str> = HashMap::new();
         for (k, v) in existing.iter() {
             updated.insert(k, v);
@@ -155,39 +591,65 @@ impl Service<'_> {
         for (k, v) in attributes.iter() {
             updated.insert(k, v);
         }
-        item.set_attributes(updated).map_err(decode_error)?;
+        self.recorder
+            .call(Operation::SetAttributes, || item.set_attributes(updated))?;
         Ok(())
     }
 
     // Given an existing item's path, delete it.
     pub(crate) fn delete(&self, path: &OwnedObjectPath) -> Result<()> {
-        let ss = self
+        let item = self
             .ss
-            .lock()
-            .expect("Mutex failure in credential store: please report a bug");
-        let item = ss.get_item_by_path(path.clone()).map_err(decode_error)?;
-        item.delete().map_err(decode_error)
+            .get_item_by_path(path.clone())
+            .map_err(decode_error)?;
+        self.recorder.call(Operation::Delete, || item.delete())
     }
 
     // Given an existing item's path, return its label.
     pub(crate) fn get_label(&self, path: &OwnedObjectPath) -> Result<String> {
-        let ss = self
+        let item = self
             .ss
-            .lock()
-            .expect("Mutex failure in credential store: please report a bug");
-        let item = ss.get_item_by_path(path.clone()).map_err(decode_error)?;
-        let label = item.get_label().map_err(decode_error)?;
+            .get_item_by_path(path.clone())
+            .map_err(decode_error)?;
+        let label = self
+            .recorder
+            .call(Operation::GetLabel, || item.get_label())?;
         Ok(label)
     }
 
     // Given an existing item's path, set its label.
     pub(crate) fn set_label(&self, path: &OwnedObjectPath, label: &str) -> Result<()> {
-        let ss = self
+        let item = self
+            .ss
+            .get_item_by_path(path.clone())
+            .map_err(decode_error)?;
+        self.recorder
+            .call(Operation::SetLabel, || item.set_label(label))
+    }
+
+    /// Given an item's path, ensure it exists and is unlocked
+    pub(crate) fn ensure_unlocked(&self, path: &OwnedObjectPath) -> Result<()> {
+        let item = self
             .ss
-            .lock()
-            .expect("Mutex failure in credential store: please report a bug");
-        let item = ss.get_item_by_path(path.clone()).map_err(decode_error)?;
-        item.set_label(label).map_err(decode_error)
+            .get_item_by_path(path.clone())
+            .map_err(decode_error)?;
+        if self
+            .recorder
+            .call(Operation::GetProperty, || item.is_locked())?
+        {
+            self.recorder.call(Operation::Unlock, || item.unlock())?;
+        }
+        Ok(())
+    }
+
+    /// Given an existing item's path, retrieve the content type of its secret.
+    pub(crate) fn get_content_type(&self, path: &OwnedObjectPath) -> Result<String> {
+        let item = self
+            .ss
+            .get_item_by_path(path.clone())
+            .map_err(decode_error)?;
+        self.recorder
+            .call(Operation::GetSecret, || item.get_secret_content_type())
     }
 }
 
@@ -195,43 +657,156 @@ impl Service<'_> {
 /// be called except from the methods of the Service struct which has
 /// made the service singleton available.
 mod util {
-    use super::{Error, Result, decode_error};
+    use super::{Error, Result};
+    use crate::diagnostics::CollectionStatus;
+    use crate::metrics::{Operation, Recorder};
 
+    use secret_service::Error as ServiceError;
     use secret_service::blocking::{Collection, SecretService};
+    use zbus::zvariant::OwnedObjectPath;
+
+    /// The prefix on a collection name that marks it as a Secret Service alias.
+    pub(crate) const ALIAS_PREFIX: &str = "alias:";
 
-    /// Find the secret service collection whose label is the given name.
+    /// Return the Secret Service alias named by a collection name, if any.
     ///
-    /// The name `default` is treated specially and is interpreted as naming
-    /// the default collection regardless of its label (which might be different).
-    pub(crate) fn get_collection<'a>(ss: &'a SecretService, name: &str) -> Result<Collection<'a>> {
+    /// The name `default` is shorthand for `alias:default`.
+    pub(crate) fn alias_of(name: &str) -> Option<&str> {
+        if name.eq("default") {
+            Some("default")
+        } else {
+            name.strip_prefix(ALIAS_PREFIX)
+        }
+    }
+
+    /// Find the secret service collection named by the given name.
+    ///
+    /// A name of the form `alias:<alias>` is resolved by looking up the
+    /// alias in the service (via `ReadAlias`). The name `default` is treated
+    /// specially and is interpreted as naming the default collection regardless
+    /// of its label (which might be different). Any other name is matched against
+    /// the labels of all the collections.
+    pub(crate) fn get_collection<'a>(
+        ss: &'a SecretService,
+        recorder: &Recorder,
+        name: &str,
+    ) -> Result<Collection<'a>> {
+        let collection = find_collection(ss, recorder, name)?;
+        if recorder.call(Operation::GetProperty, || collection.is_locked())? {
+            recorder.call(Operation::Unlock, || collection.unlock())?;
+        }
+        Ok(collection)
+    }
+
+    /// Find a collection as [get_collection] does, but without unlocking it.
+    pub(crate) fn find_collection<'a>(
+        ss: &'a SecretService,
+        recorder: &Recorder,
+        name: &str,
+    ) -> Result<Collection<'a>> {
         let collection = if name.eq("default") {
-            ss.get_default_collection().map_err(decode_error)?
+            recorder.call(Operation::FindCollection, || ss.get_default_collection())?
+        } else if let Some(alias) = alias_of(name) {
+            collection_by_alias(ss, recorder, alias)?.ok_or(Error::NoEntry)?
         } else {
-            let all = ss.get_all_collections().map_err(decode_error)?;
-            let found = all
-                .into_iter()
-                .find(|c| c.get_label().map(|l| l.eq(name)).unwrap_or(false));
+            let all = recorder.call(Operation::FindCollection, || ss.get_all_collections())?;
+            let found = all.into_iter().find(|c| {
+                recorder
+                    .call(Operation::GetLabel, || c.get_label())
+                    .map(|l| l.eq(name))
+                    .unwrap_or(false)
+            });
             found.ok_or(Error::NoEntry)?
         };
-        if collection.is_locked().map_err(decode_error)? {
-            collection.unlock().map_err(decode_error)?;
-        }
         Ok(collection)
     }
 
+    /// Find the collection with the given alias, if there is one.
+    pub(crate) fn collection_by_alias<'a>(
+        ss: &'a SecretService,
+        recorder: &Recorder,
+        alias: &str,
+    ) -> Result<Option<Collection<'a>>> {
+        recorder.call(Operation::FindCollection, || {
+            match ss.get_collection_by_alias(alias) {
+                Ok(c) => Ok(Some(c)),
+                Err(ServiceError::NoResult) => Ok(None),
+                Err(e) => Err(e),
+            }
+        })
+    }
+
+    /// Describe all the collections in the service.
+    pub(crate) fn describe_collections(
+        ss: &SecretService,
+        recorder: &Recorder,
+    ) -> Result<Vec<CollectionStatus>> {
+        let mut aliased = Vec::new();
+        for alias in ["default", "session"] {
+            if let Some(c) = collection_by_alias(ss, recorder, alias)? {
+                aliased.push((alias, c.collection_path.to_string()));
+            }
+        }
+        let mut result = Vec::new();
+        for c in recorder.call(Operation::FindCollection, || ss.get_all_collections())? {
+            let path = c.collection_path.to_string();
+            let aliases = aliased
+                .iter()
+                .filter(|(_, p)| *p == path)
+                .map(|(a, _)| a.to_string())
+                .collect();
+            result.push(CollectionStatus {
+                label: recorder.call(Operation::GetLabel, || c.get_label())?,
+                path,
+                aliases,
+                locked: recorder.call(Operation::GetProperty, || c.is_locked())?,
+            });
+        }
+        Ok(result)
+    }
+
+    /// Return the path of the collection containing an item.
+    ///
+    /// Per the Secret Service spec, item paths are formed by
+    /// appending an item identifier to their collection's path.
+    pub(crate) fn collection_path_of(item: &OwnedObjectPath) -> Result<OwnedObjectPath> {
+        let path = item.as_str();
+        let parent = match path.rfind('/') {
+            Some(i) if i > 0 => &path[..i],
+            _ => return Err(Error::Invalid("path".to_string(), path.to_string())),
+        };
+        OwnedObjectPath::try_from(parent)
+            .map_err(|_| Error::Invalid("path".to_string(), path.to_string()))
+    }
+
     /// Create a secret service collection labeled with the given name.
     ///
     /// If a collection with that name already exists, it is returned.
     ///
     /// The name `default` is specially interpreted to mean the default collection.
+    /// A name of the form `alias:<alias>` creates a collection labeled with
+    /// the alias and registers the alias for it. The `session` alias cannot
+    /// be created: the in-memory session collection is provided by the service.
     pub(crate) fn create_collection<'a>(
         ss: &'a SecretService,
+        recorder: &Recorder,
         name: &str,
     ) -> Result<Collection<'a>> {
         let collection = if name.to_ascii_lowercase().eq("default") {
-            ss.get_default_collection().map_err(decode_error)?
+            recorder.call(Operation::FindCollection, || ss.get_default_collection())?
+        } else if let Some(alias) = alias_of(name) {
+            if alias.eq("session") {
+                return Err(Error::NotSupportedByStore(
+                    "The Secret Service does not provide a session collection".to_string(),
+                ));
+            }
+            recorder.call(Operation::CreateCollection, || {
+                ss.create_collection(alias, alias)
+            })?
         } else {
-            ss.create_collection(name, "").map_err(decode_error)?
+            recorder.call(Operation::CreateCollection, || {
+                ss.create_collection(name, "")
+            })?
         };
         Ok(collection)
     }
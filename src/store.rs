@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -6,13 +6,40 @@ use keyring_core::api::CredentialStoreApi;
 use keyring_core::attributes::parse_attributes;
 use keyring_core::{Entry, Error, Result};
 
-use crate::cred::{Specifier, Wrapper};
-use crate::service::Service;
+#[cfg(feature = "archive")]
+use crate::archive::{ConflictPolicy, ImportReport};
+use crate::bulk::{DeleteFilter, DeletionReport};
+use crate::cred::{ATTRIBUTE_PREFIX, RESERVED_ATTRIBUTES, Specifier, Wrapper, expiry_of, now};
+use crate::diagnostics::{CollectionStatus, Diagnosis};
+#[cfg(feature = "fallback")]
+use crate::fallback::{FileSpecifier, FileStore, FileWrapper};
+use crate::handle;
+use crate::profile::Profile;
+use crate::resolve::ResolveMode;
+use crate::service::{ALIAS_PREFIX, Service};
+use crate::uri::CredentialUri;
 
 /// Secret service credential store
 pub struct Store {
     pub id: String,
-    pub(crate) ss: Arc<Service<'static>>,
+    pub(crate) backend: Backend,
+}
+
+/// Where a store keeps its items.
+pub(crate) enum Backend {
+    Service(Arc<Service<'static>>),
+    #[cfg(feature = "fallback")]
+    File(Arc<FileStore>),
+}
+
+impl std::fmt::Debug for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Backend::Service(_) => f.write_str("Secret Service"),
+            #[cfg(feature = "fallback")]
+            Backend::File(fs) => write!(f, "fallback file {}", fs.path.display()),
+        }
+    }
 }
 
 impl std::fmt::Debug for Store {
@@ -20,6 +47,7 @@ impl std::fmt::Debug for Store {
         f.debug_struct("Store")
             .field("vendor", &self.vendor())
             .field("id", &self.id())
+            .field("backend", &self.backend)
             .finish()
     }
 }
@@ -27,38 +55,287 @@ impl std::fmt::Debug for Store {
 impl Store {
     /// Create a new store.
     ///
-    /// Stores are not configurable.
+    /// The store uses the Secret Service, with no fallback.
     pub fn new() -> Result<Arc<Self>> {
-        Store::new_internal()
+        Store::new_internal(&HashMap::new())
     }
 
     /// Create a store with the specified configuration.
     ///
-    /// Stores are not currently configurable, this entry is provided
-    /// so configurability can be added without a major version change.
+    /// Without the `fallback` feature, stores are not configurable.
+    /// With it, these configuration keys are accepted:
+    ///
+    /// - `fallback`: when to keep items in an encrypted file rather than
+    ///   in the Secret Service. One of `never` (the default), `when-unavailable`
+    ///   (if the Secret Service can't be reached or has no default collection),
+    ///   or `always`.
+    /// - `fallback-path`: the path of the encrypted file. Defaults to
+    ///   `$XDG_DATA_HOME/keyring/secret-service-fallback.dat`.
+    /// - `fallback-passphrase`: the passphrase used to encrypt the file.
+    ///   Required unless `fallback` is `never`.
+    ///
+    /// Whether a store is using its fallback is shown in its `id` and `Debug` output,
+    /// and can be checked with [is_fallback](Store::is_fallback).
     pub fn new_with_configuration(config: &HashMap<&str, &str>) -> Result<Arc<Self>> {
-        if !config.is_empty() {
-            return Err(Error::NotSupportedByStore(
-                "Secret Service configuration is not supported".to_string(),
-            ));
-        }
-        Store::new_internal()
+        Store::new_internal(config)
     }
 
-    fn new_internal() -> Result<Arc<Self>> {
+    fn new_internal(config: &HashMap<&str, &str>) -> Result<Arc<Self>> {
         let now = SystemTime::now();
         let elapsed = if now.lt(&UNIX_EPOCH) {
             UNIX_EPOCH.duration_since(now).unwrap()
         } else {
             now.duration_since(UNIX_EPOCH).unwrap()
         };
-        let id = format!(
+        #[allow(unused_mut)]
+        let mut id = format!(
             "Crate version {}, Instantiated at {}",
             env!("CARGO_PKG_VERSION"),
             elapsed.as_secs_f64()
         );
-        let ss = Arc::new(Service::new()?);
-        Ok(Arc::new(Store { id, ss }))
+        let backend = Store::connect(config)?;
+        #[cfg(feature = "fallback")]
+        if let Backend::File(fs) = &backend {
+            id.push_str(&format!(", Using fallback file {}", fs.path.display()));
+        }
+        Ok(Arc::new(Store { id, backend }))
+    }
+
+    #[cfg(not(feature = "fallback"))]
+    fn connect(config: &HashMap<&str, &str>) -> Result<Backend> {
+        if !config.is_empty() {
+            return Err(Error::NotSupportedByStore(
+                "Secret Service configuration is not supported".to_string(),
+            ));
+        }
+        Ok(Backend::Service(Arc::new(Service::new()?)))
+    }
+
+    #[cfg(feature = "fallback")]
+    fn connect(config: &HashMap<&str, &str>) -> Result<Backend> {
+        crate::fallback::connect(config)
+    }
+
+    /// Whether this store keeps its items in a fallback file
+    /// rather than in the Secret Service.
+    pub fn is_fallback(&self) -> bool {
+        !matches!(self.backend, Backend::Service(_))
+    }
+
+    /// The Secret Service used by this store, for operations
+    /// that have no fallback equivalent.
+    pub(crate) fn service(&self) -> Result<&Arc<Service<'static>>> {
+        match &self.backend {
+            Backend::Service(ss) => Ok(ss),
+            #[cfg(feature = "fallback")]
+            Backend::File(_) => Err(Error::NotSupportedByStore(
+                "This operation needs the Secret Service, but the store is using its fallback"
+                    .to_string(),
+            )),
+        }
+    }
+
+    /// Report on the health of the Secret Service.
+    ///
+    /// This doesn't need a store (it makes its own connection to the session bus)
+    /// so it can be used to find out why [Store::new] fails.
+    pub fn diagnose() -> Diagnosis {
+        crate::diagnostics::diagnose()
+    }
+
+    /// Export matching items to an encrypted archive.
+    ///
+    /// The items are found as by [search](CredentialStoreApi::search) or, if a collection
+    /// is given, as by [search_collection](Store::search_collection). The archive
+    /// is written to `writer`, encrypted with a key derived from `passphrase`.
+    /// Returns the number of items archived.
+    #[cfg(feature = "archive")]
+    pub fn export(
+        &self,
+        spec: &HashMap<&str, &str>,
+        collection: Option<&str>,
+        passphrase: &str,
+        writer: &mut dyn std::io::Write,
+    ) -> Result<usize> {
+        crate::archive::export(self.service()?, spec, collection, passphrase, writer)
+    }
+
+    /// Import the items in an encrypted archive.
+    ///
+    /// Each item is recreated in the collection it was exported from
+    /// (which is created if necessary). The policy says what to do about
+    /// items that are already present.
+    #[cfg(feature = "archive")]
+    pub fn import(
+        &self,
+        reader: &mut dyn std::io::Read,
+        passphrase: &str,
+        policy: ConflictPolicy,
+    ) -> Result<ImportReport> {
+        crate::archive::import(self.service()?, reader, passphrase, policy)
+    }
+
+    /// A snapshot of the calls this store has made to the Secret Service.
+    ///
+    /// See the [metrics](crate::metrics) module for what's recorded.
+    /// Stores using the fallback have no metrics.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> Result<crate::metrics::Metrics> {
+        Ok(self.service()?.metrics())
+    }
+
+    /// Build the entry named by a [credential URI](crate::uri).
+    ///
+    /// The entry is the one that [build](CredentialStoreApi::build) would
+    /// return when given the service, user, and modifiers in the URI.
+    pub fn entry_from_uri(&self, uri: &str) -> Result<Entry> {
+        let uri = CredentialUri::parse(uri)?;
+        self.build(&uri.service, &uri.user, Some(&uri.all_modifiers()))
+    }
+
+    /// Replace the secret references in a text with the secrets they refer to.
+    ///
+    /// See the [resolve](crate::resolve) module for the form of the references.
+    /// In [Strict](ResolveMode::Strict) mode, a reference to an entry with
+    /// no credential is an error; in [Lenient](ResolveMode::Lenient) mode,
+    /// such a reference is left in the text.
+    pub fn resolve_secrets(&self, text: &str, mode: ResolveMode) -> Result<String> {
+        crate::resolve::resolve(self, text, mode)
+    }
+
+    /// Get an entry for the item with the given path, if there is one.
+    ///
+    /// The entry wraps the item, as entries returned by a search do.
+    /// Paths can be reused after an item is deleted, so it's better to keep
+    /// a [handle](Wrapper::handle) to an item and use
+    /// [entry_from_handle](Store::entry_from_handle).
+    pub fn entry_from_path(&self, path: &str) -> Result<Entry> {
+        let ss = self.service()?;
+        let path = handle::parse_path(path)?;
+        ss.get_created(&path)?;
+        Ok(Entry::new_with_credential(Wrapper::new(ss.clone(), path)))
+    }
+
+    /// Get an entry for the item with the given handle, if it still exists.
+    ///
+    /// Returns a [NoEntry](Error::NoEntry) error if the item has been deleted
+    /// or replaced. See the [handle](crate::handle) module for details.
+    pub fn entry_from_handle(&self, handle: &str) -> Result<Entry> {
+        let ss = self.service()?;
+        let (path, fingerprint) = handle::parse(handle)?;
+        let wrapper = Wrapper::new(ss.clone(), path);
+        if wrapper.fingerprint()? != fingerprint {
+            return Err(Error::NoEntry);
+        }
+        Ok(Entry::new_with_credential(wrapper))
+    }
+
+    /// Read the secrets of many entries, named by service and user, at once.
+    ///
+    /// The result for each entry is what [get_secret](Entry::get_secret) would
+    /// return for an entry built with no modifiers. See the [batch](crate::batch)
+    /// module for how this differs from reading the entries one at a time.
+    pub fn get_secrets(&self, entries: &[(&str, &str)]) -> Result<Vec<Result<Vec<u8>>>> {
+        crate::batch::get_secrets(self.service()?, entries)
+    }
+
+    /// Set the secrets of many entries, named by service and user, at once.
+    ///
+    /// The result for each entry is what [set_secret](Entry::set_secret) would
+    /// return for an entry built with no modifiers. See the [batch](crate::batch)
+    /// module for how this differs from setting the entries one at a time.
+    pub fn set_secrets(&self, entries: &[(&str, &str, &[u8])]) -> Result<Vec<Result<()>>> {
+        crate::batch::set_secrets(self.service()?, entries)
+    }
+
+    /// Delete the items that match both a search spec and a filter.
+    ///
+    /// The spec is interpreted as by [search](CredentialStoreApi::search), but
+    /// locked items are never unlocked: those that pass the filter are
+    /// reported as skipped. Errors on individual items are recorded in the
+    /// report and don't stop the deletion. See the [bulk](crate::bulk)
+    /// module for details.
+    pub fn delete_matching(
+        &self,
+        spec: &HashMap<&str, &str>,
+        filter: &DeleteFilter,
+    ) -> Result<DeletionReport> {
+        crate::bulk::delete_matching(self.service()?, spec, filter)
+    }
+
+    /// Delete (or, in a dry run, just list) the matching items that have expired.
+    ///
+    /// The spec is interpreted as by [delete_matching](Store::delete_matching),
+    /// so expired items that are locked are reported as skipped. Only items
+    /// whose expiry was recorded by this crate (in the `keyring:expires`
+    /// attribute) are considered, so other applications' items are left alone.
+    pub fn sweep_expired(
+        &self,
+        spec: &HashMap<&str, &str>,
+        dry_run: bool,
+    ) -> Result<DeletionReport> {
+        let now = now();
+        let filter = DeleteFilter {
+            predicate: Some(Box::new(move |item| {
+                expiry_of(&item.attributes)
+                    .map(|t| t <= now)
+                    .unwrap_or(false)
+            })),
+            dry_run,
+            ..Default::default()
+        };
+        self.delete_matching(spec, &filter)
+    }
+
+    /// Describe the collections in the Secret Service.
+    pub fn collections(&self) -> Result<Vec<CollectionStatus>> {
+        self.service()?.collections()
+    }
+
+    /// Create a collection, unless it already exists.
+    ///
+    /// The collection is named the same way as a `target` modifier,
+    /// so `alias:<name>` creates a collection with that alias.
+    pub fn create_collection(&self, collection: &str) -> Result<()> {
+        self.service()?.create_collection(collection)
+    }
+
+    /// Delete a collection and all the items in it.
+    ///
+    /// The default and session collections cannot be deleted.
+    pub fn delete_collection(&self, collection: &str) -> Result<()> {
+        self.service()?.delete_collection(collection)
+    }
+
+    /// Lock a collection.
+    pub fn lock_collection(&self, collection: &str) -> Result<()> {
+        self.service()?.set_collection_locked(collection, true)
+    }
+
+    /// Unlock a collection, prompting the user if necessary.
+    pub fn unlock_collection(&self, collection: &str) -> Result<()> {
+        self.service()?.set_collection_locked(collection, false)
+    }
+
+    /// Search a single collection for items.
+    ///
+    /// This is like [search](CredentialStoreApi::search), except that only
+    /// the collection named by `collection` is searched. The collection is
+    /// named the same way as a `target` modifier, so `default` and
+    /// `alias:session` are both allowed. Returns a
+    /// [NoEntry](Error::NoEntry) error if there is no such collection.
+    pub fn search_collection(
+        &self,
+        collection: &str,
+        spec: &HashMap<&str, &str>,
+    ) -> Result<Vec<Entry>> {
+        let ss = self.service()?;
+        let paths = ss.find_matching_items_in(collection, spec)?;
+        let mut results = Vec::with_capacity(paths.len());
+        for path in paths {
+            results.push(Entry::new_with_credential(Wrapper::new(ss.clone(), path)));
+        }
+        Ok(results)
     }
 }
 
@@ -79,7 +356,43 @@ impl CredentialStoreApi for Store {
         user: &str,
         modifiers: Option<&HashMap<&str, &str>>,
     ) -> Result<Entry> {
-        let mods = parse_attributes(&["target", "label"], modifiers)?;
+        let mut attributes = BTreeMap::new();
+        let mut others = HashMap::new();
+        for (key, value) in modifiers.into_iter().flatten() {
+            let Some(name) = key.strip_prefix(ATTRIBUTE_PREFIX) else {
+                others.insert(*key, *value);
+                continue;
+            };
+            attributes.insert(name.to_string(), value.to_string());
+        }
+        let mods = parse_attributes(
+            &[
+                "target",
+                "label",
+                "*ephemeral",
+                "expires",
+                "history",
+                "profile",
+            ],
+            Some(&others),
+        )?;
+        let profile = match mods.get("profile") {
+            None => Profile::Keyring,
+            Some(name) => Profile::from_name(name).ok_or_else(|| {
+                Error::Invalid("profile".to_string(), format!("'{name}' is not a profile"))
+            })?,
+        };
+        for name in attributes.keys() {
+            if name.is_empty()
+                || RESERVED_ATTRIBUTES.contains(&name.as_str())
+                || profile.controls(name)
+            {
+                return Err(Error::Invalid(
+                    format!("{ATTRIBUTE_PREFIX}{name}"),
+                    "must name an attribute not controlled by this store".to_string(),
+                ));
+            }
+        }
         let label = mods.get("label").map(|s| s.as_str());
         if label.map(|l| l.is_empty()).unwrap_or(false) {
             return Err(Error::Invalid(
@@ -94,13 +407,94 @@ impl CredentialStoreApi for Store {
                 "cannot be empty".to_string(),
             ));
         }
-        Ok(Entry::new_with_credential(Specifier::new(
-            self.ss.clone(),
-            label,
-            target,
-            service,
-            user,
-        )))
+        if target
+            .and_then(|t| t.strip_prefix(ALIAS_PREFIX))
+            .map(|a| a.is_empty())
+            .unwrap_or(false)
+        {
+            return Err(Error::Invalid(
+                "target".to_string(),
+                "alias cannot be empty".to_string(),
+            ));
+        }
+        let ephemeral = mods.get("ephemeral").map(|s| s.eq("true")).unwrap_or(false);
+        if ephemeral && target.is_some() {
+            return Err(Error::Invalid(
+                "ephemeral".to_string(),
+                "cannot be combined with a target".to_string(),
+            ));
+        }
+        let expires = match mods.get("expires") {
+            None => None,
+            Some(t) => Some(t.parse::<u64>().map_err(|_| {
+                Error::Invalid(
+                    "expires".to_string(),
+                    "must be a time in seconds since the Unix epoch".to_string(),
+                )
+            })?),
+        };
+        let history = match mods.get("history") {
+            None => None,
+            Some(n) => match n.parse::<usize>() {
+                Ok(n) if n > 0 => Some(n),
+                _ => {
+                    return Err(Error::Invalid(
+                        "history".to_string(),
+                        "must be a positive number".to_string(),
+                    ));
+                }
+            },
+        };
+        match &self.backend {
+            Backend::Service(ss) => Ok(Entry::new_with_credential(Specifier::new(
+                ss.clone(),
+                label,
+                target,
+                ephemeral,
+                expires,
+                history,
+                attributes,
+                profile,
+                service,
+                user,
+            ))),
+            #[cfg(feature = "fallback")]
+            Backend::File(fs) => {
+                if ephemeral {
+                    return Err(Error::NotSupportedByStore(
+                        "Ephemeral entries are not supported by the fallback store".to_string(),
+                    ));
+                }
+                if expires.is_some() {
+                    return Err(Error::NotSupportedByStore(
+                        "Expiring entries are not supported by the fallback store".to_string(),
+                    ));
+                }
+                if history.is_some() {
+                    return Err(Error::NotSupportedByStore(
+                        "Secret history is not supported by the fallback store".to_string(),
+                    ));
+                }
+                if profile != Profile::Keyring {
+                    return Err(Error::NotSupportedByStore(
+                        "Profiles are not supported by the fallback store".to_string(),
+                    ));
+                }
+                if !attributes.is_empty() {
+                    return Err(Error::NotSupportedByStore(
+                        "Identifying attributes are not supported by the fallback store"
+                            .to_string(),
+                    ));
+                }
+                Ok(Entry::new_with_credential(FileSpecifier::new(
+                    fs.clone(),
+                    label,
+                    target,
+                    service,
+                    user,
+                )))
+            }
+        }
     }
 
     /// See the keyring-core API docs.
@@ -110,15 +504,28 @@ impl CredentialStoreApi for Store {
     /// If the search matches locked items, the search will prompt the user
     /// to unlock them before returning.
     fn search(&self, spec: &HashMap<&str, &str>) -> Result<Vec<Entry>> {
-        let paths = self.ss.find_matching_items(spec)?;
-        let mut results = Vec::with_capacity(paths.len());
-        for path in paths {
-            results.push(Entry::new_with_credential(Wrapper::new(
-                self.ss.clone(),
-                path.clone(),
-            )));
+        match &self.backend {
+            Backend::Service(ss) => {
+                let paths = ss.find_matching_items(spec)?;
+                let mut results = Vec::with_capacity(paths.len());
+                for path in paths {
+                    results.push(Entry::new_with_credential(Wrapper::new(
+                        ss.clone(),
+                        path.clone(),
+                    )));
+                }
+                Ok(results)
+            }
+            #[cfg(feature = "fallback")]
+            Backend::File(fs) => {
+                let ids = fs.find_matching_items(spec)?;
+                let results = ids
+                    .into_iter()
+                    .map(|id| Entry::new_with_credential(FileWrapper::new(fs.clone(), id)))
+                    .collect();
+                Ok(results)
+            }
         }
-        Ok(results)
     }
 
     /// See the keyring-core API docs.
@@ -0,0 +1,208 @@
+/*!
+
+Structured (multi-field) credentials.
+
+A [StructuredCredential] keeps a map of named fields (say, a username,
+password, TOTP seed, and API key for one account) in the secret of a single item.
+The fields are serialized as a JSON object, and the item is given a
+`keyring:schema-version` attribute that records the version of the field
+layout.
+(The secret's content type is `text/plain` rather than `application/json`,
+because some Secret Service providers accept no other textual type.)
+
+Each structured credential is created with the schema version its caller
+expects. When an item with an older version is read, the credential's migration
+function (if any) is given the stored fields and version so it can update them,
+and the migrated fields are written back with the new version. Reading an item
+with a newer version, or an older version when there is no migration, is an error.
+
+An item without a `keyring:schema-version` attribute (for example, one
+created by [set_password](keyring_core::Entry::set_password)) is treated as having
+version 0 and a single field named `secret` that holds its (UTF-8) secret.
+A migration from version 0 is thus a way to adopt existing plain credentials.
+
+The `keyring:schema-version` attribute is controlled by this crate, so it
+can't be updated through an entry. Reading the fields of an item that has
+[expired](crate::errors::expired) is an error, as reading its secret is.
+
+Structured credentials require the Secret Service: they can't be used
+with entries in the fallback store.
+
+*/
+
+use std::collections::HashMap;
+
+use keyring_core::{Entry, Error, Result};
+
+use crate::cred::Specifier;
+
+/// The content type of a structured credential's secret.
+pub const CONTENT_TYPE: &str = "text/plain";
+
+pub use crate::cred::FIELDS_VERSION_ATTRIBUTE;
+
+/// The name of the single field of a plain (version 0) item.
+pub const PLAIN_FIELD: &str = "secret";
+
+/// A function that updates the fields of an item stored with an older schema
+/// version (its first argument) to the current schema version.
+pub type Migration = Box<dyn Fn(u32, &mut HashMap<String, String>) -> Result<()>>;
+
+/// A view of an entry's secret as a map of named fields.
+pub struct StructuredCredential<'a> {
+    specifier: &'a Specifier,
+    version: u32,
+    migration: Option<Migration>,
+}
+
+impl std::fmt::Debug for StructuredCredential<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StructuredCredential")
+            .field("specifier", &self.specifier)
+            .field("version", &self.version)
+            .field("migration", &self.migration.as_ref().map(|_| "<fn>"))
+            .finish()
+    }
+}
+
+impl<'a> StructuredCredential<'a> {
+    /// Create a structured view, with the given schema version, of an entry.
+    ///
+    /// The entry must be one built by a Secret Service store (not
+    /// one returned by a search), and the version cannot be 0.
+    pub fn new(entry: &'a Entry, version: u32) -> Result<Self> {
+        let Some(specifier) = entry.as_any().downcast_ref::<Specifier>() else {
+            return Err(Error::NotSupportedByStore(
+                "Structured credentials need an entry built by a Secret Service store".to_string(),
+            ));
+        };
+        if version == 0 {
+            return Err(Error::Invalid(
+                "version".to_string(),
+                "0 is reserved for plain items".to_string(),
+            ));
+        }
+        Ok(Self {
+            specifier,
+            version,
+            migration: None,
+        })
+    }
+
+    /// Use the given function to migrate items stored with older schema versions.
+    pub fn with_migration(
+        mut self,
+        migration: impl Fn(u32, &mut HashMap<String, String>) -> Result<()> + 'static,
+    ) -> Self {
+        self.migration = Some(Box::new(migration));
+        self
+    }
+
+    /// The schema version of this credential.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Get all the fields, migrating the item first if necessary.
+    pub fn get_fields(&self) -> Result<HashMap<String, String>> {
+        let (secret, attributes) = self.specifier.get_secret_and_attributes()?;
+        let Some(stored) = attributes.get(FIELDS_VERSION_ATTRIBUTE) else {
+            let plain =
+                String::from_utf8(secret).map_err(|e| Error::BadEncoding(e.into_bytes()))?;
+            let fields = HashMap::from([(PLAIN_FIELD.to_string(), plain)]);
+            return self.migrate(0, fields);
+        };
+        let stored: u32 = stored.parse().map_err(|_| {
+            Error::Invalid(
+                FIELDS_VERSION_ATTRIBUTE.to_string(),
+                format!("'{stored}' is not a version number"),
+            )
+        })?;
+        // the secret holds every field, so neither it nor the parse
+        // error (which can quote it) is put in the error
+        let fields = serde_json::from_slice(&secret).map_err(|e| {
+            Error::BadDataFormat(
+                Vec::new(),
+                format!(
+                    "The fields are malformed (at line {}, column {})",
+                    e.line(),
+                    e.column()
+                )
+                .into(),
+            )
+        })?;
+        self.migrate(stored, fields)
+    }
+
+    /// Replace all the fields, creating the item if necessary.
+    pub fn set_fields(&self, fields: &HashMap<&str, &str>) -> Result<()> {
+        let secret = serde_json::to_vec(fields).map_err(|e| Error::PlatformFailure(e.into()))?;
+        let version = self.version.to_string();
+        let attributes = HashMap::from([(FIELDS_VERSION_ATTRIBUTE, version.as_str())]);
+        self.specifier
+            .set_typed_secret(&secret, CONTENT_TYPE, &attributes)
+    }
+
+    /// Get a single field, if it's present.
+    pub fn get_field(&self, name: &str) -> Result<Option<String>> {
+        Ok(self.get_fields()?.remove(name))
+    }
+
+    /// Set a single field, leaving the others alone.
+    ///
+    /// If there is no item, one is created with just this field.
+    pub fn set_field(&self, name: &str, value: &str) -> Result<()> {
+        let mut fields = match self.get_fields() {
+            Ok(fields) => fields,
+            Err(Error::NoEntry) => HashMap::new(),
+            Err(err) => return Err(err),
+        };
+        fields.insert(name.to_string(), value.to_string());
+        self.set_fields(&borrow_fields(&fields))
+    }
+
+    /// Remove a single field, leaving the others alone.
+    pub fn remove_field(&self, name: &str) -> Result<()> {
+        let mut fields = self.get_fields()?;
+        if fields.remove(name).is_some() {
+            self.set_fields(&borrow_fields(&fields))?;
+        }
+        Ok(())
+    }
+
+    /// Bring fields stored with the given version up to this credential's version.
+    fn migrate(
+        &self,
+        stored: u32,
+        mut fields: HashMap<String, String>,
+    ) -> Result<HashMap<String, String>> {
+        if stored == self.version {
+            return Ok(fields);
+        }
+        let unsupported = |why: &str| {
+            Error::Invalid(
+                FIELDS_VERSION_ATTRIBUTE.to_string(),
+                format!(
+                    "item has version {stored}, expected {}: {why}",
+                    self.version
+                ),
+            )
+        };
+        if stored > self.version {
+            return Err(unsupported("the item is newer than this credential"));
+        }
+        let Some(migration) = &self.migration else {
+            return Err(unsupported("no migration was given"));
+        };
+        migration(stored, &mut fields)?;
+        self.set_fields(&borrow_fields(&fields))?;
+        Ok(fields)
+    }
+}
+
+fn borrow_fields(fields: &HashMap<String, String>) -> HashMap<&str, &str> {
+    fields
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect()
+}
@@ -3,7 +3,11 @@ use std::sync::{Arc, Once};
 
 use super::{Store, cred::Specifier};
 use crate::cred::Wrapper;
-use keyring_core::{CredentialStore, Entry, Error, api::CredentialPersistence, get_default_store};
+use keyring_core::{
+    CredentialStore, Entry, Error,
+    api::{CredentialPersistence, CredentialStoreApi},
+    get_default_store,
+};
 
 static SET_STORE: Once = Once::new();
 
@@ -113,13 +117,795 @@ fn test_invalid_parameter() {
     let modifiers = HashMap::from([("target", "")]);
     let entry = Entry::new_with_modifiers("service", "user", &modifiers);
     assert!(matches!(entry, Err(Error::Invalid(_, _))));
+    let modifiers = HashMap::from([("target", "alias:")]);
+    let entry = Entry::new_with_modifiers("service", "user", &modifiers);
+    assert!(matches!(entry, Err(Error::Invalid(_, _))));
+    let modifiers = HashMap::from([("ephemeral", "yes")]);
+    let entry = Entry::new_with_modifiers("service", "user", &modifiers);
+    assert!(matches!(entry, Err(Error::Invalid(_, _))));
+    let modifiers = HashMap::from([("ephemeral", "true"), ("target", "foo")]);
+    let entry = Entry::new_with_modifiers("service", "user", &modifiers);
+    assert!(matches!(entry, Err(Error::Invalid(_, _))));
     let modifiers = HashMap::from([("label", "")]);
     let entry = Entry::new_with_modifiers("service", "user", &modifiers);
     assert!(matches!(entry, Err(Error::Invalid(_, _))));
+    let modifiers = HashMap::from([("expires", "tomorrow")]);
+    let entry = Entry::new_with_modifiers("service", "user", &modifiers);
+    assert!(matches!(&entry, Err(Error::Invalid(_, _))));
+    assert!(!crate::errors::is_expired(&entry.unwrap_err()));
+    let modifiers = HashMap::from([("history", "0")]);
+    let entry = Entry::new_with_modifiers("service", "user", &modifiers);
+    assert!(matches!(entry, Err(Error::Invalid(_, _))));
     let store = Store::new_with_configuration(&HashMap::from([("anything", "anything")]));
     assert!(matches!(store, Err(Error::NotSupportedByStore(_))));
 }
 
+#[test]
+fn test_decode_error() {
+    use crate::errors::{Cause, decode_error};
+    use secret_service::Error as ServiceError;
+    use zbus::fdo::Error as FdoError;
+
+    let err = decode_error(ServiceError::ZbusFdo(FdoError::ServiceUnknown("".into())));
+    assert!(matches!(err, Error::NoStorageAccess(_)));
+    assert!(matches!(
+        Cause::of(&err),
+        Some(Cause::ServiceUnavailable(_))
+    ));
+    // the tests run with a session bus, so an unavailable service
+    // means there's no provider rather than no bus
+    let err = decode_error(ServiceError::Unavailable);
+    assert!(matches!(
+        Cause::of(&err),
+        Some(Cause::ServiceUnavailable(_))
+    ));
+    let err = decode_error(ServiceError::Zbus(zbus::Error::Address("".into())));
+    assert!(matches!(Cause::of(&err), Some(Cause::BusUnavailable(_))));
+    let err = decode_error(ServiceError::ZbusFdo(FdoError::UnknownObject("".into())));
+    assert!(matches!(err, Error::NoEntry));
+    for err in [
+        FdoError::UnknownMethod("".into()),
+        FdoError::UnknownInterface("".into()),
+    ] {
+        let err = decode_error(ServiceError::ZbusFdo(err));
+        assert!(matches!(err, Error::PlatformFailure(_)));
+        assert!(matches!(Cause::of(&err), Some(Cause::Other(_))));
+    }
+    let err = decode_error(ServiceError::ZbusFdo(FdoError::NotSupported("".into())));
+    assert!(matches!(err, Error::PlatformFailure(_)));
+    assert!(matches!(
+        Cause::of(&err),
+        Some(Cause::UnsupportedAlgorithm(_))
+    ));
+    let err = decode_error(ServiceError::Prompt);
+    assert!(matches!(Cause::of(&err), Some(Cause::PromptDismissed(_))));
+    let err = decode_error(ServiceError::ZbusFdo(FdoError::AccessDenied("".into())));
+    assert!(matches!(Cause::of(&err), Some(Cause::PermissionDenied(_))));
+    let err = decode_error(ServiceError::Locked);
+    assert!(matches!(Cause::of(&err), Some(Cause::Locked(_))));
+    let err = decode_error(ServiceError::Crypto("bad"));
+    assert!(matches!(err, Error::PlatformFailure(_)));
+    assert!(matches!(Cause::of(&err), Some(Cause::Other(_))));
+    let err = crate::errors::expired(42);
+    assert!(crate::errors::is_expired(&err));
+    assert!(matches!(Cause::of(&err), Some(Cause::Expired(42))));
+    assert!(Cause::of(&err).unwrap().service_error().is_none());
+    let err = crate::errors::conflict();
+    assert!(crate::errors::is_conflict(&err));
+    assert!(!crate::errors::is_expired(&err));
+    // other errors about the secret aren't conflicts
+    let err = Error::Invalid("secret".to_string(), "the copy does not match".to_string());
+    assert!(!crate::errors::is_conflict(&err));
+}
+
+#[test]
+fn test_diagnose() {
+    let report = Store::diagnose();
+    assert!(report.bus_reachable, "{report}");
+    assert!(report.service_available, "{report}");
+    assert!(report.service_owner.is_some());
+    assert!(report.algorithm.is_some());
+    assert!(report.default_collection);
+    assert!(
+        report
+            .collections
+            .iter()
+            .any(|c| c.aliases.contains(&"default".to_string()))
+    );
+}
+
+#[cfg(feature = "fallback")]
+fn fallback_store(path: &std::path::Path, passphrase: &str) -> keyring_core::Result<Arc<Store>> {
+    let path = path.to_str().unwrap();
+    let config = HashMap::from([
+        ("fallback", "always"),
+        ("fallback-path", path),
+        ("fallback-passphrase", passphrase),
+    ]);
+    Store::new_with_configuration(&config)
+}
+
+#[test]
+#[cfg(feature = "fallback")]
+fn test_fallback_configuration() {
+    let config = HashMap::from([("fallback", "sometimes"), ("fallback-passphrase", "x")]);
+    let store = Store::new_with_configuration(&config);
+    assert!(matches!(store, Err(Error::Invalid(_, _))));
+    let config = HashMap::from([("fallback", "always")]);
+    let store = Store::new_with_configuration(&config);
+    assert!(matches!(store, Err(Error::Invalid(_, _))));
+    let path = std::env::temp_dir().join(generate_random_string());
+    let store = fallback_store(&path, "passphrase").unwrap();
+    assert!(store.is_fallback());
+    assert!(store.id().contains(path.to_str().unwrap()));
+    assert!(format!("{store:?}").contains("fallback file"));
+    let modifiers = HashMap::from([("ephemeral", "true")]);
+    let entry = store.build("service", "user", Some(&modifiers));
+    assert!(matches!(entry, Err(Error::NotSupportedByStore(_))));
+}
+
+#[test]
+#[cfg(feature = "fallback")]
+fn test_fallback_round_trip() {
+    let path = std::env::temp_dir().join(generate_random_string());
+    let store = fallback_store(&path, "passphrase").unwrap();
+    let name = generate_random_string();
+    let entry = store.build(&name, &name, None).unwrap();
+    assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+    test_round_trip_no_delete("fallback password", &entry, "fallback password");
+    entry
+        .update_attributes(&HashMap::from([("random", "bar")]))
+        .unwrap();
+    entry
+        .update_attributes(&HashMap::from([("service", "bar")]))
+        .unwrap_err();
+    // the file is encrypted
+    let contents = std::fs::read(&path).unwrap();
+    let needle = b"fallback password";
+    assert!(!contents.windows(needle.len()).any(|w| w == needle));
+    // a second store on the same file sees the same items
+    let store2 = fallback_store(&path, "passphrase").unwrap();
+    let found = store2.search(&HashMap::from([("random", "bar")])).unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].get_password().unwrap(), "fallback password");
+    assert_eq!(
+        found[0].get_specifiers().unwrap(),
+        (name.clone(), name.clone())
+    );
+    // but not with the wrong passphrase
+    let store3 = fallback_store(&path, "wrong passphrase");
+    assert!(matches!(store3, Err(Error::BadDataFormat(ref data, _)) if data.is_empty()));
+    test_round_trip("fallback update", &entry, "updated password");
+    // a malformed file is reported without quoting its decrypted contents
+    let key = crate::crypto::SealingKey::new("passphrase").unwrap();
+    let sealed = key
+        .seal(crate::fallback::MAGIC, br#"[{"secret": "hunter2-stored"#)
+        .unwrap();
+    std::fs::write(&path, sealed).unwrap();
+    let err = fallback_store(&path, "passphrase").unwrap_err();
+    assert!(matches!(err, Error::BadStoreFormat(_)));
+    assert!(!err.to_string().contains("hunter2"));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+#[cfg(feature = "archive")]
+fn test_export_import() {
+    use crate::archive::ConflictPolicy;
+
+    let store = Store::new().unwrap();
+    let name1 = generate_random_string();
+    let name2 = generate_random_string();
+    let entry1 = store.build(&name1, &name1, None).unwrap();
+    entry1.set_password("entry1 password").unwrap();
+    let entry2 = store.build(&name1, &name2, None).unwrap();
+    let secret = generate_random_bytes();
+    entry2.set_secret(&secret).unwrap();
+    entry2
+        .update_attributes(&HashMap::from([("random", "bar")]))
+        .unwrap();
+    let spec = HashMap::from([("service", name1.as_str())]);
+    let mut archive = Vec::new();
+    let count = store
+        .export(&spec, None, "passphrase", &mut archive)
+        .unwrap();
+    assert_eq!(count, 2);
+    let needle = b"entry1 password";
+    assert!(!archive.windows(needle.len()).any(|w| w == needle));
+    // existing items are skipped or overwritten
+    entry1.set_password("changed password").unwrap();
+    let report = store
+        .import(&mut archive.as_slice(), "passphrase", ConflictPolicy::Skip)
+        .unwrap();
+    assert_eq!((report.created, report.skipped), (0, 2));
+    assert_eq!(entry1.get_password().unwrap(), "changed password");
+    let report = store
+        .import(
+            &mut archive.as_slice(),
+            "passphrase",
+            ConflictPolicy::Overwrite,
+        )
+        .unwrap();
+    assert_eq!((report.created, report.overwritten), (0, 2));
+    assert_eq!(entry1.get_password().unwrap(), "entry1 password");
+    // missing items are created
+    entry1.delete_credential().unwrap();
+    entry2.delete_credential().unwrap();
+    let report = store
+        .import(&mut archive.as_slice(), "passphrase", ConflictPolicy::Skip)
+        .unwrap();
+    assert_eq!((report.created, report.skipped), (2, 0));
+    assert_eq!(entry1.get_password().unwrap(), "entry1 password");
+    assert_eq!(entry2.get_secret().unwrap(), secret);
+    assert_eq!(entry2.get_attributes().unwrap()["random"], "bar");
+    // a wrong passphrase is detected
+    let result = store.import(&mut archive.as_slice(), "wrong", ConflictPolicy::Skip);
+    assert!(matches!(result, Err(Error::BadDataFormat(_, _))));
+    // archives that decrypt but don't parse don't reveal their secrets in errors
+    let key = crate::crypto::SealingKey::new("passphrase").unwrap();
+    for plain in [
+        r#"{"version":1,"items":[{"secret":"hunter2-archived""#,
+        r#"{"version":1,"items":[{"secret":"hunter2-archived"}]}"#,
+        r#"{"version":99,"items":[],"secret":"hunter2-archived"}"#,
+    ] {
+        let sealed = key.seal(crate::archive::MAGIC, plain.as_bytes()).unwrap();
+        let result = store.import(&mut sealed.as_slice(), "passphrase", ConflictPolicy::Skip);
+        let Err(Error::BadDataFormat(data, err)) = result else {
+            panic!("Malformed archive was accepted");
+        };
+        assert!(data.is_empty());
+        assert!(!err.to_string().contains("hunter2"));
+    }
+    entry1.delete_credential().unwrap();
+    entry2.delete_credential().unwrap();
+}
+
+#[test]
+fn test_delete_matching() {
+    use crate::bulk::DeleteFilter;
+
+    let store = Store::new().unwrap();
+    let name = generate_random_string();
+    let keep = store.build(&name, "keep-me", None).unwrap();
+    keep.set_password("keep").unwrap();
+    let mut leaked = Vec::new();
+    for i in 0..3 {
+        let modifiers = HashMap::from([("label", "leaked test credential")]);
+        let entry = store
+            .build(&name, &format!("leaked-{i}"), Some(&modifiers))
+            .unwrap();
+        entry.set_password("leaked").unwrap();
+        leaked.push(entry);
+    }
+    let spec = HashMap::from([("service", name.as_str())]);
+    let mut filter = DeleteFilter {
+        label: Some("leaked * credential".to_string()),
+        attributes: HashMap::from([("username".to_string(), "leaked-?".to_string())]),
+        predicate: Some(Box::new(|item| !item.attributes["username"].ends_with('2'))),
+        dry_run: true,
+    };
+    let report = store.delete_matching(&spec, &filter).unwrap();
+    assert_eq!(report.deleted.len(), 2);
+    assert!(report.failed.is_empty());
+    for entry in leaked.iter() {
+        entry.get_password().unwrap();
+    }
+    filter.dry_run = false;
+    let report = store.delete_matching(&spec, &filter).unwrap();
+    assert_eq!(report.deleted.len(), 2);
+    assert!(matches!(leaked[0].get_password(), Err(Error::NoEntry)));
+    assert!(matches!(leaked[1].get_password(), Err(Error::NoEntry)));
+    leaked[2].get_password().unwrap();
+    keep.get_password().unwrap();
+    let report = store
+        .delete_matching(&spec, &DeleteFilter::default())
+        .unwrap();
+    assert_eq!(report.deleted.len(), 2);
+    assert!(matches!(keep.get_password(), Err(Error::NoEntry)));
+}
+
+#[test]
+#[cfg(feature = "structured")]
+fn test_structured_credential() {
+    use crate::structured::{FIELDS_VERSION_ATTRIBUTE, StructuredCredential};
+
+    let name = generate_random_string();
+    let entry = entry_new(&name, &name);
+    entry.set_password("plain password").unwrap();
+    // plain items need a migration from version 0
+    let v1 = StructuredCredential::new(&entry, 1).unwrap();
+    assert!(matches!(v1.get_fields(), Err(Error::Invalid(_, _))));
+    let v1 = v1.with_migration(|from, fields| {
+        assert_eq!(from, 0);
+        let password = fields.remove("secret").unwrap();
+        fields.insert("password".to_string(), password);
+        Ok(())
+    });
+    assert_eq!(
+        v1.get_field("password").unwrap().as_deref(),
+        Some("plain password")
+    );
+    let attributes = entry.get_attributes().unwrap();
+    assert_eq!(attributes[FIELDS_VERSION_ATTRIBUTE], "1");
+    assert!(matches!(
+        entry.update_attributes(&HashMap::from([(FIELDS_VERSION_ATTRIBUTE, "2")])),
+        Err(Error::Invalid(key, _)) if key == FIELDS_VERSION_ATTRIBUTE
+    ));
+    // field-level updates leave other fields alone
+    v1.set_field("totp-seed", "JBSWY3DPEHPK3PXP").unwrap();
+    v1.set_field("password", "new password").unwrap();
+    let fields = v1.get_fields().unwrap();
+    assert_eq!(fields.len(), 2);
+    assert_eq!(fields["password"], "new password");
+    assert_eq!(fields["totp-seed"], "JBSWY3DPEHPK3PXP");
+    let json: HashMap<String, String> =
+        serde_json::from_slice(&entry.get_secret().unwrap()).unwrap();
+    assert_eq!(json, fields);
+    v1.remove_field("totp-seed").unwrap();
+    assert_eq!(v1.get_field("totp-seed").unwrap(), None);
+    // malformed fields are reported without the secret
+    entry.set_password("{\"password\": \"hunter2").unwrap();
+    match v1.get_fields() {
+        Err(Error::BadDataFormat(data, err)) => {
+            assert!(data.is_empty());
+            assert!(!err.to_string().contains("hunter2"));
+        }
+        other => panic!("Expected BadDataFormat, got {other:?}"),
+    }
+    v1.set_fields(&HashMap::from([("password", "new password")]))
+        .unwrap();
+    // newer items can't be read by older credentials
+    let v2 = StructuredCredential::new(&entry, 2)
+        .unwrap()
+        .with_migration(|from, fields| {
+            assert_eq!(from, 1);
+            fields.insert("api-key".to_string(), "none".to_string());
+            Ok(())
+        });
+    assert_eq!(v2.get_field("api-key").unwrap().as_deref(), Some("none"));
+    assert!(matches!(v1.get_fields(), Err(Error::Invalid(_, _))));
+    assert!(StructuredCredential::new(&entry, 0).is_err());
+    entry.delete_credential().unwrap();
+    // expired items can't be read
+    let earlier = (crate::cred::now() - 1).to_string();
+    let modifiers = HashMap::from([("expires", earlier.as_str())]);
+    let store = Store::new().unwrap();
+    let expiring = store.build(&name, &name, Some(&modifiers)).unwrap();
+    let v1 = StructuredCredential::new(&expiring, 1).unwrap();
+    v1.set_field("password", "stale").unwrap();
+    assert!(crate::errors::is_expired(&v1.get_fields().unwrap_err()));
+    expiring.delete_credential().unwrap();
+}
+
+#[test]
+fn test_expiring_entries() {
+    use crate::cred::{EXPIRES_ATTRIBUTE, now};
+    use crate::errors::is_expired;
+
+    let store = Store::new().unwrap();
+    let name = generate_random_string();
+    let later = (now() + 3600).to_string();
+    let modifiers = HashMap::from([("expires", later.as_str())]);
+    let entry = store.build(&name, &name, Some(&modifiers)).unwrap();
+    entry.set_password("fresh token").unwrap();
+    assert_eq!(entry.get_password().unwrap(), "fresh token");
+    assert_eq!(entry.get_attributes().unwrap()[EXPIRES_ATTRIBUTE], later);
+    let result = entry.update_attributes(&HashMap::from([(EXPIRES_ATTRIBUTE, "0")]));
+    assert!(matches!(result, Err(Error::Invalid(_, _))));
+    // items of other applications, with expiry attributes this crate
+    // didn't write, are never treated as expired
+    let ss = store.service().unwrap();
+    for (user, key) in [("foreign", "expires"), ("garbled", EXPIRES_ATTRIBUTE)] {
+        let attributes =
+            HashMap::from([("service", name.as_str()), ("username", user), (key, "0x0")]);
+        ss.create_item("default", user, attributes, b"theirs", "text/plain", true)
+            .unwrap();
+        let foreign = store.build(&name, user, None).unwrap();
+        assert_eq!(foreign.get_password().unwrap(), "theirs");
+    }
+    let spec = HashMap::from([("service", name.as_str())]);
+    let report = store.sweep_expired(&spec, false).unwrap();
+    assert!(report.deleted.is_empty());
+    for user in ["foreign", "garbled"] {
+        store
+            .build(&name, user, None)
+            .unwrap()
+            .delete_credential()
+            .unwrap();
+    }
+    let report = store.sweep_expired(&spec, false).unwrap();
+    assert!(report.deleted.is_empty());
+    // setting the secret of an existing item updates its expiry
+    let earlier = (now() - 1).to_string();
+    let modifiers = HashMap::from([("expires", earlier.as_str())]);
+    let stale = store.build(&name, &name, Some(&modifiers)).unwrap();
+    stale.set_password("stale token").unwrap();
+    assert!(is_expired(&entry.get_password().unwrap_err()));
+    let found = store.search(&spec).unwrap();
+    assert_eq!(found.len(), 1);
+    assert!(is_expired(&found[0].get_password().unwrap_err()));
+    let report = store.sweep_expired(&spec, true).unwrap();
+    assert_eq!(report.deleted.len(), 1);
+    assert!(is_expired(&entry.get_password().unwrap_err()));
+    let report = store.sweep_expired(&spec, false).unwrap();
+    assert_eq!(report.deleted.len(), 1);
+    assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+}
+
+#[test]
+fn test_secret_history() {
+    use crate::history::HISTORY_ID_ATTRIBUTE;
+
+    let store = Store::new().unwrap();
+    let name = generate_random_string();
+    let modifiers = HashMap::from([("history", "2")]);
+    let entry = store.build(&name, &name, Some(&modifiers)).unwrap();
+    let specifier: &Specifier = entry.as_any().downcast_ref().unwrap();
+    for password in ["v1", "v2", "v3", "v3", "v4"] {
+        entry.set_password(password).unwrap();
+    }
+    // unchanged secrets aren't archived, and only two versions are kept
+    let versions = specifier.list_versions().unwrap();
+    let numbers: Vec<u32> = versions.iter().map(|v| v.version).collect();
+    assert_eq!(numbers, vec![3, 2]);
+    assert_eq!(specifier.get_version(3).unwrap(), b"v3");
+    assert_eq!(specifier.get_version(2).unwrap(), b"v2");
+    assert!(matches!(specifier.get_version(1), Err(Error::NoEntry)));
+    // history items aren't found by entries
+    assert_eq!(
+        store
+            .search(&HashMap::from([("service", name.as_str())]))
+            .unwrap()
+            .len(),
+        1
+    );
+    // a rollback archives the secret it replaces
+    specifier.rollback(2).unwrap();
+    assert_eq!(entry.get_password().unwrap(), "v2");
+    let numbers: Vec<u32> = specifier
+        .list_versions()
+        .unwrap()
+        .iter()
+        .map(|v| v.version)
+        .collect();
+    assert_eq!(numbers, vec![4, 3]);
+    assert_eq!(specifier.get_version(4).unwrap(), b"v4");
+    // deleting the credential deletes its history
+    let id = entry.get_attributes().unwrap()[HISTORY_ID_ATTRIBUTE].clone();
+    let result = entry.update_attributes(&HashMap::from([(HISTORY_ID_ATTRIBUTE, "x")]));
+    assert!(matches!(result, Err(Error::Invalid(_, _))));
+    entry.delete_credential().unwrap();
+    let spec = HashMap::from([(HISTORY_ID_ATTRIBUTE, id.as_str())]);
+    assert!(store.search(&spec).unwrap().is_empty());
+    // items of other applications that happen to use the same
+    // (unnamespaced) attributes aren't treated as history
+    let ss = store.service().unwrap();
+    let attributes = HashMap::from([
+        ("service", name.as_str()),
+        ("username", name.as_str()),
+        ("history-id", name.as_str()),
+    ]);
+    ss.create_item("default", "head", attributes, b"head", "text/plain", true)
+        .unwrap();
+    let attributes = HashMap::from([("history-id", name.as_str()), ("history-version", "1")]);
+    ss.create_item("default", "version", attributes, b"old", "text/plain", true)
+        .unwrap();
+    entry.delete_credential().unwrap();
+    let spec = HashMap::from([("history-id", name.as_str())]);
+    let found = store.search(&spec).unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].get_password().unwrap(), "old");
+    found[0].delete_credential().unwrap();
+}
+
+#[test]
+fn test_history_bulk_deletion() {
+    use crate::bulk::DeleteFilter;
+    use crate::cred::now;
+    use crate::history::HISTORY_ID_ATTRIBUTE;
+
+    let store = Store::new().unwrap();
+    // bulk deletion deletes the history of the items it deletes
+    let name = generate_random_string();
+    let modifiers = HashMap::from([("history", "2")]);
+    let entry = store.build(&name, &name, Some(&modifiers)).unwrap();
+    entry.set_password("v1").unwrap();
+    entry.set_password("v2").unwrap();
+    let id = entry.get_attributes().unwrap()[HISTORY_ID_ATTRIBUTE].clone();
+    let history = HashMap::from([(HISTORY_ID_ATTRIBUTE, id.as_str())]);
+    assert_eq!(store.search(&history).unwrap().len(), 2);
+    let spec = HashMap::from([("service", name.as_str())]);
+    let report = store
+        .delete_matching(&spec, &DeleteFilter::default())
+        .unwrap();
+    assert_eq!(report.deleted.len(), 1);
+    assert!(report.failed.is_empty());
+    assert!(store.search(&history).unwrap().is_empty());
+    // a search that finds both an item and its history deletes each once
+    entry.set_password("v1").unwrap();
+    entry.set_password("v2").unwrap();
+    let id = entry.get_attributes().unwrap()[HISTORY_ID_ATTRIBUTE].clone();
+    let history = HashMap::from([(HISTORY_ID_ATTRIBUTE, id.as_str())]);
+    let report = store
+        .delete_matching(&history, &DeleteFilter::default())
+        .unwrap();
+    assert!(report.failed.is_empty());
+    assert!(store.search(&history).unwrap().is_empty());
+    // so does sweeping expired items
+    let earlier = (now() - 1).to_string();
+    let modifiers = HashMap::from([("history", "2"), ("expires", earlier.as_str())]);
+    let entry = store.build(&name, &name, Some(&modifiers)).unwrap();
+    entry.set_password("v1").unwrap();
+    entry.set_password("v2").unwrap();
+    let found = store.search(&spec).unwrap();
+    let id = found[0].get_attributes().unwrap()[HISTORY_ID_ATTRIBUTE].clone();
+    let history = HashMap::from([(HISTORY_ID_ATTRIBUTE, id.as_str())]);
+    assert_eq!(store.search(&history).unwrap().len(), 2);
+    let report = store.sweep_expired(&spec, false).unwrap();
+    assert_eq!(report.deleted.len(), 1);
+    assert!(store.search(&history).unwrap().is_empty());
+}
+
+#[test]
+fn test_compare_and_set() {
+    use crate::errors::is_conflict;
+
+    let store = Store::new().unwrap();
+    let name = generate_random_string();
+    let entry = store.build(&name, &name, None).unwrap();
+    let specifier: &Specifier = entry.as_any().downcast_ref().unwrap();
+    assert!(matches!(
+        specifier.compare_and_set(b"", b"new"),
+        Err(Error::NoEntry)
+    ));
+    entry.set_secret(b"0").unwrap();
+    specifier.compare_and_set(b"0", b"1").unwrap();
+    assert!(is_conflict(
+        &specifier.compare_and_set(b"0", b"2").unwrap_err()
+    ));
+    assert_eq!(entry.get_secret().unwrap(), b"1");
+    // concurrent increments through the same store never lose an update
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let store = store.clone();
+            let name = name.clone();
+            std::thread::spawn(move || {
+                let entry = store.build(&name, &name, None).unwrap();
+                let specifier: &Specifier = entry.as_any().downcast_ref().unwrap();
+                for _ in 0..5 {
+                    loop {
+                        let current = entry.get_secret().unwrap();
+                        let n: u32 = String::from_utf8(current.clone()).unwrap().parse().unwrap();
+                        match specifier.compare_and_set(&current, (n + 1).to_string().as_bytes()) {
+                            Ok(()) => break,
+                            Err(err) if is_conflict(&err) => continue,
+                            Err(err) => panic!("Unexpected error: {err:?}"),
+                        }
+                    }
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    assert_eq!(entry.get_secret().unwrap(), b"21");
+    // an update through another connection (a separate store) is detected
+    // once it has been made; concurrent updates through separate connections
+    // can race (see the compare_and_set docs), so they aren't tested
+    let other_store = Store::new().unwrap();
+    let other = other_store.build(&name, &name, None).unwrap();
+    let other_specifier: &Specifier = other.as_any().downcast_ref().unwrap();
+    other_specifier.compare_and_set(b"21", b"22").unwrap();
+    assert!(is_conflict(
+        &specifier.compare_and_set(b"21", b"23").unwrap_err()
+    ));
+    specifier.compare_and_set(b"22", b"23").unwrap();
+    assert_eq!(other.get_secret().unwrap(), b"23");
+    entry.delete_credential().unwrap();
+    // only a successful compare-and-set archives the replaced secret
+    let modifiers = HashMap::from([("history", "2")]);
+    let entry = store.build(&name, &name, Some(&modifiers)).unwrap();
+    let specifier: &Specifier = entry.as_any().downcast_ref().unwrap();
+    entry.set_secret(b"a").unwrap();
+    assert!(is_conflict(
+        &specifier.compare_and_set(b"x", b"b").unwrap_err()
+    ));
+    assert!(specifier.list_versions().unwrap().is_empty());
+    specifier.compare_and_set(b"a", b"b").unwrap();
+    assert_eq!(specifier.list_versions().unwrap().len(), 1);
+    assert_eq!(specifier.get_version(1).unwrap(), b"a");
+    entry.delete_credential().unwrap();
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn test_metrics() {
+    use crate::metrics::Operation;
+    let store = Store::new().unwrap();
+    let metrics = store.metrics().unwrap();
+    assert_eq!(metrics.get(Operation::Connect).calls, 1);
+    assert_eq!(metrics.calls(), 1);
+    let name = generate_random_string();
+    let entry = store.build(&name, &name, None).unwrap();
+    assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+    entry.set_password("test metrics").unwrap();
+    entry.set_password("test metrics again").unwrap();
+    assert_eq!(entry.get_password().unwrap(), "test metrics again");
+    entry
+        .update_attributes(&HashMap::from([("comment", "metrics")]))
+        .unwrap();
+    assert_eq!(entry.get_attributes().unwrap()["comment"], "metrics");
+    entry.delete_credential().unwrap();
+    store.collections().unwrap();
+    let metrics = store.metrics().unwrap();
+    assert_eq!(metrics.get(Operation::SetAttributes).calls, 1);
+    assert!(metrics.get(Operation::GetAttributes).calls >= 3);
+    assert!(metrics.get(Operation::FindCollection).calls >= 1);
+    assert!(metrics.get(Operation::GetLabel).calls >= 1);
+    assert_eq!(
+        metrics.prompt_capable_calls,
+        metrics.get(Operation::Unlock).calls + metrics.get(Operation::CreateCollection).calls
+    );
+    assert!(metrics.get(Operation::Search).calls >= 4);
+    assert_eq!(metrics.get(Operation::CreateItem).calls, 1);
+    assert_eq!(metrics.get(Operation::SetSecret).calls, 1);
+    assert_eq!(metrics.get(Operation::GetSecret).calls, 1);
+    assert_eq!(metrics.get(Operation::Delete).calls, 1);
+    assert_eq!(metrics.errors(), 0);
+    assert!(metrics.total() >= metrics.get(Operation::Search).total);
+    assert!(metrics.to_string().contains("create-item: 1 calls"));
+}
+
+#[test]
+fn test_batched_secrets() {
+    let store = Store::new().unwrap();
+    let name = generate_random_string();
+    let users: Vec<String> = (0..5).map(|i| format!("user-{i}")).collect();
+    let secrets: Vec<Vec<u8>> = (0..5).map(|_| generate_random_bytes()).collect();
+    let existing = store.build(&name, &users[0], None).unwrap();
+    existing.set_password("to be replaced").unwrap();
+    let updates: Vec<(&str, &str, &[u8])> = users
+        .iter()
+        .zip(secrets.iter())
+        .map(|(u, s)| (name.as_str(), u.as_str(), s.as_slice()))
+        .collect();
+    let results = store.set_secrets(&updates).unwrap();
+    assert!(results.iter().all(|r| r.is_ok()));
+    let mut names: Vec<(&str, &str)> = users.iter().map(|u| (name.as_str(), u.as_str())).collect();
+    names.push((name.as_str(), "missing"));
+    let results = store.get_secrets(&names).unwrap();
+    assert_eq!(results.len(), 6);
+    for (result, secret) in results.iter().zip(secrets.iter()) {
+        assert_eq!(result.as_ref().unwrap(), secret);
+    }
+    assert!(matches!(results[5], Err(Error::NoEntry)));
+    // ambiguity is reported per entry
+    let modifiers = HashMap::from([("target", "alias:session")]);
+    let twin = store.build(&name, &users[1], Some(&modifiers)).unwrap();
+    twin.set_password("twin").unwrap();
+    let results = store.get_secrets(&names[..2]).unwrap();
+    assert!(results[0].is_ok());
+    assert!(matches!(results[1], Err(Error::Ambiguous(_))));
+    twin.delete_credential().unwrap();
+    for user in users.iter() {
+        store
+            .build(&name, user, None)
+            .unwrap()
+            .delete_credential()
+            .unwrap();
+    }
+}
+
+#[test]
+fn test_migrate() {
+    use crate::migrate::{MigrationOptions, Outcome, migrate};
+    use keyring_core::sample;
+
+    let source = sample::Store::new().unwrap();
+    let dest = Store::new().unwrap();
+    let name1 = generate_random_string();
+    let name2 = generate_random_string();
+    let original1 = source.build(&name1, &name1, None).unwrap();
+    original1.set_password("password1").unwrap();
+    original1
+        .update_attributes(&HashMap::from([("comment", "from sample")]))
+        .unwrap();
+    let original2 = source.build(&name1, &name2, None).unwrap();
+    original2.set_password("password2").unwrap();
+    let spec = HashMap::from([("service", name1.as_str())]);
+    let mut options = MigrationOptions {
+        dry_run: true,
+        ..Default::default()
+    };
+    let report = migrate(source.as_ref(), &spec, &dest, &options).unwrap();
+    assert_eq!(report.records.len(), 2);
+    assert!(
+        report
+            .records
+            .iter()
+            .all(|r| matches!(r.outcome, Outcome::WouldCopy))
+    );
+    assert!(report.to_string().contains("would copy"));
+    let copy1 = dest.build(&name1, &name1, None).unwrap();
+    assert!(matches!(copy1.get_password(), Err(Error::NoEntry)));
+    options.dry_run = false;
+    options.delete_originals = true;
+    let report = migrate(source.as_ref(), &spec, &dest, &options).unwrap();
+    assert!(!report.has_failures(), "{report}");
+    assert!(
+        report
+            .records
+            .iter()
+            .all(|r| matches!(r.outcome, Outcome::Moved))
+    );
+    assert_eq!(copy1.get_password().unwrap(), "password1");
+    assert_eq!(copy1.get_attributes().unwrap()["comment"], "from sample");
+    let copy2 = dest.build(&name1, &name2, None).unwrap();
+    assert_eq!(copy2.get_password().unwrap(), "password2");
+    assert!(matches!(original1.get_password(), Err(Error::NoEntry)));
+    assert!(matches!(original2.get_password(), Err(Error::NoEntry)));
+    copy1.delete_credential().unwrap();
+    copy2.delete_credential().unwrap();
+}
+
+#[test]
+fn test_migrate_failed_copy() {
+    use crate::migrate::{MigrationOptions, Outcome, migrate};
+    use keyring_core::sample;
+
+    let source = sample::Store::new().unwrap();
+    let dest = Store::new().unwrap();
+    let name = generate_random_string();
+    let original = source.build(&name, &name, None).unwrap();
+    original.set_password("password").unwrap();
+    original
+        .update_attributes(&HashMap::from([("comment", "from sample")]))
+        .unwrap();
+    let spec = HashMap::from([("service", name.as_str())]);
+    // the destination is identified by a different comment, so the original's can't be copied
+    let options = MigrationOptions {
+        modifiers: HashMap::from([("attribute:comment".to_string(), "other".to_string())]),
+        ..Default::default()
+    };
+    let copy = dest
+        .build(
+            &name,
+            &name,
+            Some(&HashMap::from([("attribute:comment", "other")])),
+        )
+        .unwrap();
+    for _ in 0..2 {
+        let report = migrate(source.as_ref(), &spec, &dest, &options).unwrap();
+        assert!(
+            matches!(report.records[0].outcome, Outcome::Failed(Error::Invalid(ref key, _)) if key == "comment"),
+            "{report}"
+        );
+        assert!(matches!(copy.get_password(), Err(Error::NoEntry)));
+        assert_eq!(original.get_password().unwrap(), "password");
+    }
+    // but an identifying attribute with the same value is left alone
+    let options = MigrationOptions {
+        modifiers: HashMap::from([("attribute:comment".to_string(), "from sample".to_string())]),
+        ..Default::default()
+    };
+    let report = migrate(source.as_ref(), &spec, &dest, &options).unwrap();
+    assert!(
+        matches!(report.records[0].outcome, Outcome::Copied),
+        "{report}"
+    );
+    let copy = dest
+        .build(
+            &name,
+            &name,
+            Some(&HashMap::from([("attribute:comment", "from sample")])),
+        )
+        .unwrap();
+    assert_eq!(copy.get_password().unwrap(), "password");
+    copy.delete_credential().unwrap();
+    original.delete_credential().unwrap();
+}
+
 #[test]
 fn test_missing_entry() {
     let name = generate_random_string();
@@ -307,6 +1093,658 @@ fn test_entries_with_and_without_targets() {
         .unwrap_err();
 }
 
+#[test]
+fn test_move_to_collection() {
+    let name = generate_random_string();
+    let entry = entry_new(&name, &name);
+    entry.set_password("test move").unwrap();
+    let wrapper = entry.get_credential().unwrap();
+    let moved = wrapper
+        .as_any()
+        .downcast_ref::<Wrapper>()
+        .unwrap()
+        .move_to_collection("alias:session")
+        .unwrap();
+    assert!(matches!(wrapper.get_password(), Err(Error::NoEntry)));
+    assert_eq!(moved.get_attributes().unwrap()["target"], "alias:session");
+    assert_eq!(moved.get_password().unwrap(), "test move");
+    // entries without a target still find the moved item
+    assert_eq!(entry.get_password().unwrap(), "test move");
+    let session = entry_new_with_target("alias:session", &name, &name);
+    assert_eq!(session.get_password().unwrap(), "test move");
+    let specifier: &Specifier = session.as_any().downcast_ref().unwrap();
+    let retargeted = specifier.retarget("default").unwrap();
+    assert!(matches!(session.get_password(), Err(Error::NoEntry)));
+    assert_eq!(retargeted.get_attributes().unwrap()["target"], "default");
+    assert_eq!(retargeted.get_password().unwrap(), "test move");
+    assert!(matches!(
+        specifier.retarget("alias:"),
+        Err(Error::Invalid(key, _)) if key == "target"
+    ));
+    retargeted.delete_credential().unwrap();
+    assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+}
+
+#[test]
+fn test_rename() {
+    let name = generate_random_string();
+    let new_name = generate_random_string();
+    let entry = entry_new(&name, &name);
+    entry.set_password("test rename").unwrap();
+    let specifier: &Specifier = entry.as_any().downcast_ref().unwrap();
+    let renamed = specifier.rename(&new_name, &name, false).unwrap();
+    assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+    assert_eq!(renamed.get_password().unwrap(), "test rename");
+    let attributes = renamed.get_attributes().unwrap();
+    assert_eq!(attributes["service"], new_name);
+    assert_eq!(attributes["username"], name);
+    let specifier: &Specifier = renamed.as_any().downcast_ref().unwrap();
+    assert_eq!(specifier.label, format!("keyring:{name}@{new_name}"));
+    // without relabel, the item keeps its old label
+    assert_eq!(
+        specifier.get_label().unwrap(),
+        format!("keyring:{name}@{name}")
+    );
+    // a rename that would create ambiguity is refused
+    entry.set_password("in the way").unwrap();
+    assert!(matches!(
+        specifier.rename(&name, &name, true),
+        Err(Error::Invalid(key, _)) if key == "specifier"
+    ));
+    entry.delete_credential().unwrap();
+    let renamed = specifier.rename(&new_name, &new_name, true).unwrap();
+    let specifier: &Specifier = renamed.as_any().downcast_ref().unwrap();
+    // the item's label wasn't the default for its service and user, so it's kept
+    assert_eq!(
+        specifier.get_label().unwrap(),
+        format!("keyring:{name}@{name}")
+    );
+    specifier.set_label(&specifier.label).unwrap();
+    let renamed = specifier.rename(&name, &new_name, true).unwrap();
+    let specifier: &Specifier = renamed.as_any().downcast_ref().unwrap();
+    assert_eq!(
+        specifier.get_label().unwrap(),
+        format!("keyring:{new_name}@{name}")
+    );
+    assert_eq!(renamed.get_password().unwrap(), "test rename");
+    renamed.delete_credential().unwrap();
+}
+
+#[test]
+fn test_wrapper_collection() {
+    let name = generate_random_string();
+    let entry = entry_new(&name, &name);
+    entry.set_password("in the default collection").unwrap();
+    let session = entry_new_with_target("alias:session", &name, &name);
+    session.set_password("in the session collection").unwrap();
+    let Err(Error::Ambiguous(wrappers)) = entry.get_password() else {
+        panic!("Two items weren't ambiguous");
+    };
+    assert_eq!(wrappers.len(), 2);
+    let mut aliases: Vec<String> = Vec::new();
+    for wrapper in wrappers.iter() {
+        let wrapper: &Wrapper = wrapper.as_any().downcast_ref().unwrap();
+        let collection = wrapper.get_collection().unwrap();
+        assert!(
+            wrapper
+                .get_path()
+                .starts_with(&format!("{}/", collection.path))
+        );
+        assert!(format!("{wrapper:?}").contains(&collection.path));
+        assert_eq!(wrapper.to_string(), wrapper.get_path());
+        aliases.extend(collection.aliases);
+    }
+    assert!(aliases.contains(&"default".to_string()));
+    assert!(aliases.contains(&"session".to_string()));
+    session.delete_credential().unwrap();
+    entry.delete_credential().unwrap();
+}
+
+#[test]
+fn test_wrapper_to_specifier() {
+    let name = generate_random_string();
+    let store = Store::new().unwrap();
+    let modifiers = HashMap::from([("ephemeral", "true"), ("label", "ephemeral label")]);
+    let entry = store.build(&name, &name, Some(&modifiers)).unwrap();
+    entry.set_password("test to_specifier").unwrap();
+    let found = store
+        .search(&HashMap::from([("service", name.as_str())]))
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    let wrapper: &Wrapper = found[0].as_any().downcast_ref().unwrap();
+    let converted = wrapper.to_specifier().unwrap();
+    let specifier: &Specifier = converted.as_any().downcast_ref().unwrap();
+    assert_eq!(specifier.service, name);
+    assert_eq!(specifier.user, name);
+    assert_eq!(specifier.label, "ephemeral label");
+    assert!(specifier.ephemeral);
+    assert!(specifier.target.is_none());
+    assert_eq!(converted.get_password().unwrap(), "test to_specifier");
+    converted.delete_credential().unwrap();
+    // recreating the item through the converted entry puts it back in the same place
+    converted.set_password("test to_specifier again").unwrap();
+    assert_eq!(entry.get_password().unwrap(), "test to_specifier again");
+    let attributes = HashMap::from([("foreign-attribute", name.as_str())]);
+    let path = store
+        .service()
+        .unwrap()
+        .create_item(
+            "alias:session",
+            "foreign",
+            attributes,
+            b"secret",
+            "text/plain",
+            false,
+        )
+        .unwrap();
+    let foreign_entry =
+        Entry::new_with_credential(Wrapper::new(store.service().unwrap().clone(), path));
+    let foreign: &Wrapper = foreign_entry.as_any().downcast_ref().unwrap();
+    assert!(matches!(
+        foreign.to_specifier(),
+        Err(Error::Invalid(key, _)) if key == "service"
+    ));
+    foreign_entry.delete_credential().unwrap();
+    entry.delete_credential().unwrap();
+}
+
+#[test]
+fn test_item_handles() {
+    let name = generate_random_string();
+    let store = Store::new().unwrap();
+    let entry = store.build(&name, &name, None).unwrap();
+    entry.set_password("test handles").unwrap();
+    let wrapper = entry.get_credential().unwrap();
+    let wrapper: &Wrapper = wrapper.as_any().downcast_ref().unwrap();
+    let handle = wrapper.handle().unwrap();
+    assert!(handle.starts_with(&format!("{}#", wrapper.get_path())));
+    let by_path = store.entry_from_path(&wrapper.get_path()).unwrap();
+    assert_eq!(by_path.get_password().unwrap(), "test handles");
+    let by_handle = store.entry_from_handle(&handle).unwrap();
+    assert_eq!(by_handle.get_password().unwrap(), "test handles");
+    // changes to the secret and other attributes don't invalidate the handle
+    entry.set_password("test handles again").unwrap();
+    entry
+        .update_attributes(&HashMap::from([("handle-test", "yes")]))
+        .unwrap();
+    let by_handle = store.entry_from_handle(&handle).unwrap();
+    assert_eq!(by_handle.get_password().unwrap(), "test handles again");
+    let fingerprint = handle.rsplit_once('#').unwrap().1;
+    let wrong = handle.replace(fingerprint, "0123456789abcdef");
+    assert!(matches!(
+        store.entry_from_handle(&wrong),
+        Err(Error::NoEntry)
+    ));
+    for bad in ["no-fingerprint", "/path#xyz", "not a path#0123456789abcdef"] {
+        assert!(matches!(
+            store.entry_from_handle(bad),
+            Err(Error::Invalid(key, _)) if key == "handle"
+        ));
+    }
+    assert!(matches!(
+        store.entry_from_path("not a path"),
+        Err(Error::Invalid(key, _)) if key == "path"
+    ));
+    entry.delete_credential().unwrap();
+    assert!(matches!(
+        store.entry_from_path(&wrapper.get_path()),
+        Err(Error::NoEntry)
+    ));
+    assert!(matches!(
+        store.entry_from_handle(&handle),
+        Err(Error::NoEntry)
+    ));
+}
+
+#[test]
+fn test_handle_fingerprints() {
+    use crate::cred::IDENTIFYING_ATTRIBUTE;
+    use crate::handle::fingerprint;
+    use crate::profile::{NETWORK_PASSWORD_SCHEMA, SCHEMA_ATTRIBUTE};
+
+    let attributes = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    };
+    let server = |protocol: &str| {
+        attributes(&[
+            (SCHEMA_ATTRIBUTE, NETWORK_PASSWORD_SCHEMA),
+            ("server", "example.com"),
+            ("user", "alice"),
+            ("protocol", protocol),
+        ])
+    };
+    // items that differ only in an attribute of their profile's schema differ
+    assert_ne!(
+        fingerprint(1, &server("smb")),
+        fingerprint(1, &server("sftp"))
+    );
+    assert_eq!(
+        fingerprint(1, &server("smb")),
+        fingerprint(1, &server("smb"))
+    );
+    // as do items that differ only in their recorded identifying attributes
+    let host = |host: &str, note: &str| {
+        attributes(&[
+            ("service", "svc"),
+            ("username", "alice"),
+            ("host", host),
+            ("note", note),
+            (IDENTIFYING_ATTRIBUTE, "host"),
+        ])
+    };
+    assert_ne!(
+        fingerprint(1, &host("a", "x")),
+        fingerprint(1, &host("b", "x"))
+    );
+    // but other attributes don't count
+    assert_eq!(
+        fingerprint(1, &host("a", "x")),
+        fingerprint(1, &host("a", "y"))
+    );
+    assert_ne!(
+        fingerprint(1, &host("a", "x")),
+        fingerprint(2, &host("a", "x"))
+    );
+}
+
+#[test]
+fn test_credential_uris() {
+    use crate::uri::CredentialUri;
+    let store = Store::new().unwrap();
+    let name = generate_random_string();
+    let modifiers = HashMap::from([
+        ("target", "alias:session"),
+        ("label", "a label/with?odd&chars=%"),
+        ("history", "2"),
+    ]);
+    let service = format!("{name} service/é");
+    let built = store
+        .build(&service, "user@host", Some(&modifiers))
+        .unwrap();
+    let specifier: &Specifier = built.as_any().downcast_ref().unwrap();
+    let uri = specifier.to_string();
+    assert!(uri.starts_with("secret-service://alias%3Asession/"));
+    let parsed: CredentialUri = uri.parse().unwrap();
+    assert_eq!(parsed.target.as_deref(), Some("alias:session"));
+    assert_eq!(parsed.service, service);
+    assert_eq!(parsed.user, "user@host");
+    assert_eq!(parsed.to_string(), uri);
+    let from_uri = store.entry_from_uri(&uri).unwrap();
+    assert_eq!(format!("{from_uri:?}"), format!("{built:?}"));
+    // default labels and absent targets are left out
+    let plain = store.build(&name, &name, None).unwrap();
+    let plain_uri = format!("secret-service:///{name}/{name}");
+    let specifier: &Specifier = plain.as_any().downcast_ref().unwrap();
+    assert_eq!(specifier.to_string(), plain_uri);
+    let from_uri = store.entry_from_uri(&plain_uri).unwrap();
+    assert_eq!(format!("{from_uri:?}"), format!("{plain:?}"));
+    // wrappers show the URI of the specifier that finds their item,
+    // which doesn't keep history
+    built.set_password("test uris").unwrap();
+    let wrapper = built.get_credential().unwrap();
+    let wrapper: &Wrapper = wrapper.as_any().downcast_ref().unwrap();
+    assert_eq!(wrapper.uri().unwrap(), uri.replace("history=2&", ""));
+    built.delete_credential().unwrap();
+    for bad in [
+        "keyring:///service/user",
+        "secret-service://service/user",
+        "secret-service:///service/user/extra",
+        "secret-service:///service/user?label",
+        "secret-service:///service/user?target=default",
+        "secret-service:///service/user?label=a&label=b",
+        "secret-service:///serv%zzice/user",
+    ] {
+        assert!(
+            matches!(store.entry_from_uri(bad), Err(Error::Invalid(key, _)) if key == "uri"),
+            "{bad} was accepted"
+        );
+    }
+    assert!(matches!(
+        store.entry_from_uri("secret-service:///service/user?color=blue"),
+        Err(Error::Invalid(key, _)) if key == "color"
+    ));
+}
+
+#[test]
+fn test_resolve_secrets() {
+    use crate::resolve::ResolveMode;
+    let store = Store::new().unwrap();
+    let name = generate_random_string();
+    let plain = store.build(&name, "db", None).unwrap();
+    plain.set_password("hunter2").unwrap();
+    let modifiers = HashMap::from([("target", "alias:session")]);
+    let session = store.build(&name, "api/key", Some(&modifiers)).unwrap();
+    session.set_password("s3cr3t").unwrap();
+    let text = format!(
+        "db: ${{secret-service:{name}/db}}\n\
+         again: ${{secret-service:{name}/db}}\n\
+         api: ${{secret-service:alias%3Asession/{name}/api%2Fkey}}\n\
+         home: ${{HOME}}\n"
+    );
+    let resolved = store.resolve_secrets(&text, ResolveMode::Strict).unwrap();
+    assert_eq!(
+        resolved,
+        "db: hunter2\nagain: hunter2\napi: s3cr3t\nhome: ${HOME}\n"
+    );
+    let missing = format!("${{secret-service:{name}/missing}}");
+    let text = format!("{text}missing: {missing}\n");
+    assert!(matches!(
+        store.resolve_secrets(&text, ResolveMode::Strict),
+        Err(Error::Invalid(key, msg)) if key == "placeholder" && msg.contains(&missing)
+    ));
+    let resolved = store.resolve_secrets(&text, ResolveMode::Lenient).unwrap();
+    assert!(resolved.starts_with("db: hunter2\n"));
+    assert!(resolved.ends_with(&format!("missing: {missing}\n")));
+    for bad in [
+        "${secret-service:no-user}",
+        "${secret-service:a/b/c/d}",
+        "${secret-service:unterminated/user",
+        "${secret-service:bad%zzencoding/user}",
+    ] {
+        assert!(matches!(
+            store.resolve_secrets(bad, ResolveMode::Lenient),
+            Err(Error::Invalid(key, _)) if key == "placeholder"
+        ));
+    }
+    plain.delete_credential().unwrap();
+    session.delete_credential().unwrap();
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn test_resolve_batching() {
+    use crate::metrics::Operation;
+    use crate::resolve::ResolveMode;
+    let store = Store::new().unwrap();
+    let name = generate_random_string();
+    let entries: Vec<Entry> = (0..3)
+        .map(|i| {
+            let entry = store.build(&name, &format!("user-{i}"), None).unwrap();
+            entry.set_password(&format!("secret-{i}")).unwrap();
+            entry
+        })
+        .collect();
+    let modifiers = HashMap::from([("target", "alias:session")]);
+    let session = store.build(&name, "session", Some(&modifiers)).unwrap();
+    session.set_password("in session").unwrap();
+    let text = format!(
+        "${{secret-service:{name}/user-0}} ${{secret-service:{name}/user-1}} \
+         ${{secret-service:{name}/user-2}} ${{secret-service:{name}/user-0}} \
+         ${{secret-service:alias%3Asession/{name}/session}}"
+    );
+    let before = store.metrics().unwrap();
+    let resolved = store.resolve_secrets(&text, ResolveMode::Strict).unwrap();
+    let after = store.metrics().unwrap();
+    assert_eq!(resolved, "secret-0 secret-1 secret-2 secret-0 in session");
+    let calls = |op| after.get(op).calls - before.get(op).calls;
+    // one search and one fetch per distinct entry; the test service would
+    // prompt to unlock, so the single unlock of a batch isn't checked
+    assert_eq!(calls(Operation::Search), 4);
+    assert_eq!(calls(Operation::GetSecret), 4);
+    assert_eq!(calls(Operation::Unlock), 0);
+    for entry in entries {
+        entry.delete_credential().unwrap();
+    }
+    session.delete_credential().unwrap();
+}
+
+#[test]
+fn test_identifying_attributes() {
+    let store = Store::new().unwrap();
+    let name = generate_random_string();
+    let build = |host: &str| {
+        let modifiers = HashMap::from([("attribute:host", host), ("attribute:port", "443")]);
+        store.build(&name, &name, Some(&modifiers)).unwrap()
+    };
+    let first = build("one.example.com");
+    let second = build("two.example.com");
+    first.set_password("first host").unwrap();
+    assert!(matches!(second.get_password(), Err(Error::NoEntry)));
+    second.set_password("second host").unwrap();
+    assert_eq!(first.get_password().unwrap(), "first host");
+    let attributes = second.get_attributes().unwrap();
+    assert_eq!(attributes["host"], "two.example.com");
+    assert_eq!(attributes["port"], "443");
+    assert_eq!(attributes[crate::cred::IDENTIFYING_ATTRIBUTE], "host,port");
+    // an entry without the attributes finds both items
+    let plain = store.build(&name, &name, None).unwrap();
+    assert!(matches!(plain.get_password(), Err(Error::Ambiguous(_))));
+    assert!(matches!(
+        first.update_attributes(&HashMap::from([("host", "three.example.com")])),
+        Err(Error::Invalid(key, _)) if key == "host"
+    ));
+    first
+        .update_attributes(&HashMap::from([("note", "not identifying")]))
+        .unwrap();
+    let specifier: &Specifier = first.as_any().downcast_ref().unwrap();
+    let uri = specifier.to_string();
+    assert!(uri.contains("attribute%3Ahost=one.example.com"));
+    let from_uri = store.entry_from_uri(&uri).unwrap();
+    assert_eq!(from_uri.get_password().unwrap(), "first host");
+    // wrappers recover the identifying attributes from their items
+    let wrapper = first.get_credential().unwrap();
+    let wrapper: &Wrapper = wrapper.as_any().downcast_ref().unwrap();
+    assert_eq!(wrapper.uri().unwrap(), uri);
+    let converted = wrapper.to_specifier().unwrap();
+    let specifier: &Specifier = converted.as_any().downcast_ref().unwrap();
+    assert_eq!(specifier.attributes["host"], "one.example.com");
+    assert_eq!(specifier.attributes["port"], "443");
+    assert_eq!(converted.get_password().unwrap(), "first host");
+    let from_uri = store.entry_from_uri(&wrapper.uri().unwrap()).unwrap();
+    assert_eq!(from_uri.get_password().unwrap(), "first host");
+    for key in ["attribute:", "attribute:service", "attribute:target"] {
+        let modifiers = HashMap::from([(key, "value")]);
+        assert!(matches!(
+            store.build(&name, &name, Some(&modifiers)),
+            Err(Error::Invalid(k, _)) if k == key
+        ));
+    }
+    first.delete_credential().unwrap();
+    second.delete_credential().unwrap();
+}
+
+#[test]
+fn test_network_password_profile() {
+    use crate::profile::{NETWORK_PASSWORD_SCHEMA, Profile, SCHEMA_ATTRIBUTE};
+    let store = Store::new().unwrap();
+    let server = format!("{}.example.com", generate_random_string());
+    // an item saved by a GNOME application
+    let attributes = HashMap::from([
+        (SCHEMA_ATTRIBUTE, NETWORK_PASSWORD_SCHEMA),
+        ("user", "alice"),
+        ("server", server.as_str()),
+        ("protocol", "smb"),
+        ("domain", "WORKGROUP"),
+        ("object", "share"),
+    ]);
+    store
+        .service()
+        .unwrap()
+        .create_item(
+            "default",
+            "saved by GNOME",
+            attributes,
+            b"gnome password",
+            "text/plain",
+            false,
+        )
+        .unwrap();
+    let modifiers = HashMap::from([
+        ("profile", "network-password"),
+        ("attribute:protocol", "smb"),
+        ("attribute:object", "share"),
+    ]);
+    let entry = store.build(&server, "alice", Some(&modifiers)).unwrap();
+    assert_eq!(entry.get_password().unwrap(), "gnome password");
+    // plain entries don't find network passwords
+    let plain = store.build(&server, "alice", None).unwrap();
+    assert!(matches!(plain.get_password(), Err(Error::NoEntry)));
+    let found = entry.get_credential().unwrap();
+    let wrapper: &Wrapper = found.as_any().downcast_ref().unwrap();
+    assert_eq!(
+        found.get_specifiers(),
+        Some((server.clone(), "alice".to_string()))
+    );
+    let converted = wrapper.to_specifier().unwrap();
+    let specifier: &Specifier = converted.as_any().downcast_ref().unwrap();
+    assert_eq!(specifier.profile, Profile::NetworkPassword);
+    assert_eq!(specifier.attributes["domain"], "WORKGROUP");
+    assert_eq!(specifier.label, "saved by GNOME");
+    // updates through a wrapper keep the GNOME item's content type
+    found.set_password("updated password").unwrap();
+    assert_eq!(entry.get_password().unwrap(), "updated password");
+    let ss = store.service().unwrap();
+    let keys = HashMap::from([("server", server.as_str()), ("user", "alice")]);
+    let paths = ss.find_matching_items(&keys).unwrap();
+    assert_eq!(
+        ss.exclusive().get_content_type(&paths[0]).unwrap(),
+        "text/plain"
+    );
+    assert!(matches!(
+        entry.update_attributes(&HashMap::from([("server", "elsewhere")])),
+        Err(Error::Invalid(key, _)) if key == "server"
+    ));
+    entry.delete_credential().unwrap();
+    // items created through the profile are laid out as GNOME does
+    entry.set_password("new password").unwrap();
+    let attributes = entry.get_attributes().unwrap();
+    assert_eq!(attributes[SCHEMA_ATTRIBUTE], NETWORK_PASSWORD_SCHEMA);
+    assert_eq!(attributes["server"], server);
+    assert_eq!(attributes["user"], "alice");
+    assert_eq!(attributes["protocol"], "smb");
+    assert!(!attributes.contains_key("service"));
+    let specifier: &Specifier = entry.as_any().downcast_ref().unwrap();
+    assert_eq!(
+        specifier.get_label().unwrap(),
+        format!("smb://alice@{server}/share")
+    );
+    let from_uri = store.entry_from_uri(&specifier.to_string()).unwrap();
+    assert_eq!(from_uri.get_password().unwrap(), "new password");
+    entry.delete_credential().unwrap();
+    let modifiers = HashMap::from([("profile", "network-password"), ("attribute:server", "x")]);
+    assert!(matches!(
+        store.build(&server, "alice", Some(&modifiers)),
+        Err(Error::Invalid(key, _)) if key == "attribute:server"
+    ));
+    let modifiers = HashMap::from([("profile", "no-such-profile")]);
+    assert!(matches!(
+        store.build(&server, "alice", Some(&modifiers)),
+        Err(Error::Invalid(key, _)) if key == "profile"
+    ));
+}
+
+#[test]
+fn test_keytar_profile() {
+    use crate::profile::{GENERIC_SCHEMA, SCHEMA_ATTRIBUTE};
+    let store = Store::new().unwrap();
+    let service = generate_random_string();
+    // an item saved by keytar
+    let attributes = HashMap::from([
+        (SCHEMA_ATTRIBUTE, GENERIC_SCHEMA),
+        ("service", service.as_str()),
+        ("account", "alice"),
+    ]);
+    let label = format!("{service}/alice");
+    store
+        .service()
+        .unwrap()
+        .create_item(
+            "default",
+            &label,
+            attributes,
+            b"keytar password",
+            "text/plain",
+            false,
+        )
+        .unwrap();
+    let modifiers = HashMap::from([("profile", "keytar")]);
+    let entry = store.build(&service, "alice", Some(&modifiers)).unwrap();
+    assert_eq!(entry.get_password().unwrap(), "keytar password");
+    let specifier: &Specifier = entry.as_any().downcast_ref().unwrap();
+    assert_eq!(specifier.label, label);
+    // plain entries don't find keytar items
+    let plain = store.build(&service, "alice", None).unwrap();
+    assert!(matches!(plain.get_password(), Err(Error::NoEntry)));
+    assert!(matches!(
+        entry.update_attributes(&HashMap::from([("account", "bob")])),
+        Err(Error::Invalid(key, _)) if key == "account"
+    ));
+    entry.delete_credential().unwrap();
+    // items created through the profile are laid out as keytar does
+    let bob = store.build(&service, "bob", Some(&modifiers)).unwrap();
+    bob.set_password("bob's password").unwrap();
+    let attributes = bob.get_attributes().unwrap();
+    assert_eq!(attributes[SCHEMA_ATTRIBUTE], GENERIC_SCHEMA);
+    assert_eq!(attributes["service"], service);
+    assert_eq!(attributes["account"], "bob");
+    assert!(!attributes.contains_key("username"));
+    let specifier: &Specifier = bob.as_any().downcast_ref().unwrap();
+    assert_eq!(specifier.get_label().unwrap(), format!("{service}/bob"));
+    let found = store
+        .search(&HashMap::from([("service", service.as_str())]))
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(
+        found[0].get_specifiers(),
+        Some((service.clone(), "bob".to_string()))
+    );
+    // updates through a wrapper keep keytar's content type
+    found[0].set_password("bob's new password").unwrap();
+    assert_eq!(bob.get_password().unwrap(), "bob's new password");
+    let ss = store.service().unwrap();
+    let keys = HashMap::from([("service", service.as_str()), ("account", "bob")]);
+    let paths = ss.find_matching_items(&keys).unwrap();
+    assert_eq!(
+        ss.exclusive().get_content_type(&paths[0]).unwrap(),
+        "text/plain"
+    );
+    bob.delete_credential().unwrap();
+}
+
+#[test]
+fn test_entries_with_alias_target() {
+    let name1 = generate_random_string();
+    let name2 = generate_random_string();
+    let entry = entry_new_with_target("alias:session", &name1, &name2);
+    entry.set_password("session password").unwrap();
+    assert_eq!(entry.get_password().unwrap(), "session password");
+    let attributes = entry.get_attributes().unwrap();
+    assert_eq!(attributes["target"], "alias:session");
+    let specifier = entry.as_any().downcast_ref::<Specifier>().unwrap();
+    assert!(matches!(
+        specifier.delete_target(),
+        Err(Error::NotSupportedByStore(_))
+    ));
+    entry.delete_credential().unwrap();
+    assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+}
+
+#[test]
+fn test_ephemeral_entries() {
+    let name1 = generate_random_string();
+    let name2 = generate_random_string();
+    let store = Store::new().unwrap();
+    let modifiers = HashMap::from([("ephemeral", "true")]);
+    let entry = store.build(&name1, &name2, Some(&modifiers)).unwrap();
+    entry.set_password("ephemeral password").unwrap();
+    assert_eq!(entry.get_password().unwrap(), "ephemeral password");
+    let attributes = entry.get_attributes().unwrap();
+    assert!(!attributes.contains_key("target"));
+    // the item is found by a service-wide search, just like persistent items
+    let search = HashMap::from([("service", name1.as_str()), ("username", name2.as_str())]);
+    assert_eq!(store.search(&search).unwrap().len(), 1);
+    // but it lives only in the session collection
+    let in_session = store.search_collection("alias:session", &search).unwrap();
+    assert_eq!(in_session.len(), 1);
+    let in_default = store.search_collection("default", &search).unwrap();
+    assert!(in_default.is_empty());
+    entry.delete_credential().unwrap();
+    assert!(matches!(entry.get_password(), Err(Error::NoEntry)));
+}
+
 #[test]
 fn test_create_then_move() {
     let name = generate_random_string();
@@ -0,0 +1,159 @@
+/*!
+
+URIs for credentials.
+
+A credential can be named by a single string, suitable for configuration files,
+of the form:
+
+```text
+secret-service://<target>/<service>/<user>?<modifier>=<value>&...
+```
+
+The `<target>` is the value of the entry's `target` modifier, and is empty if
+it has none (as in `secret-service:///my-service/alice`). The query is optional,
+and gives the entry's other modifiers (`label`, `ephemeral`, `expires`,
+`history`, `profile`, and `attribute:<name>`); a `target` modifier can't be given in the query. Every part of the
+URI is percent-encoded (see [RFC 3986](https://www.rfc-editor.org/rfc/rfc3986)),
+so targets, services, users, and modifier values can contain any characters.
+For example, the entry built with service `my app`, user `alice`, and the modifiers
+`target` of `alias:session` and `label` of `My App` is named by
+`secret-service://alias%3Asession/my%20app/alice?label=My%20App`.
+
+[Store::entry_from_uri](crate::Store::entry_from_uri) builds the entry that a URI
+names, exactly as [build](keyring_core::api::CredentialStoreApi::build) would.
+Going the other way, the `Display` implementation of
+[Specifier](crate::cred::Specifier) produces its URI, as does
+[Wrapper::uri](crate::cred::Wrapper::uri) for the item a wrapper wraps.
+(A specifier's URI only gives its label if that isn't the default one.)
+
+*/
+
+use std::collections::{BTreeMap, HashMap};
+
+use keyring_core::{Error, Result};
+
+/// The scheme of credential URIs.
+pub const SCHEME: &str = "secret-service";
+
+/// The parts of a credential URI.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CredentialUri {
+    pub target: Option<String>,
+    pub service: String,
+    pub user: String,
+    /// The modifiers (other than `target`) given in the query.
+    pub modifiers: BTreeMap<String, String>,
+}
+
+impl CredentialUri {
+    /// Parse a credential URI.
+    pub fn parse(uri: &str) -> Result<Self> {
+        let invalid = |why: &str| Error::Invalid("uri".to_string(), format!("'{uri}' {why}"));
+        let rest = uri
+            .strip_prefix(SCHEME)
+            .and_then(|r| r.strip_prefix("://"))
+            .ok_or_else(|| invalid(&format!("doesn't start with {SCHEME}://")))?;
+        let (rest, query) = match rest.split_once('?') {
+            Some((rest, query)) => (rest, Some(query)),
+            None => (rest, None),
+        };
+        let parts: Vec<&str> = rest.split('/').collect();
+        let [target, service, user] = parts[..] else {
+            return Err(invalid("isn't of the form <target>/<service>/<user>"));
+        };
+        let decode = |part: &str| decode(part).ok_or_else(|| invalid("has a bad percent-encoding"));
+        let target = match decode(target)? {
+            t if t.is_empty() => None,
+            t => Some(t),
+        };
+        let mut modifiers = BTreeMap::new();
+        for pair in query.into_iter().flat_map(|q| q.split('&')) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| invalid("has a query parameter with no value"))?;
+            let key = decode(key)?;
+            if key == "target" {
+                return Err(invalid("has a target in its query"));
+            }
+            if modifiers.insert(key, decode(value)?).is_some() {
+                return Err(invalid("has a repeated query parameter"));
+            }
+        }
+        Ok(Self {
+            target,
+            service: decode(service)?,
+            user: decode(user)?,
+            modifiers,
+        })
+    }
+
+    /// All the modifiers named by the URI, including the target.
+    pub fn all_modifiers(&self) -> HashMap<&str, &str> {
+        let mut result: HashMap<&str, &str> = self
+            .modifiers
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        if let Some(target) = &self.target {
+            result.insert("target", target.as_str());
+        }
+        result
+    }
+}
+
+impl std::str::FromStr for CredentialUri {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl std::fmt::Display for CredentialUri {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{SCHEME}://{}/{}/{}",
+            encode(self.target.as_deref().unwrap_or("")),
+            encode(&self.service),
+            encode(&self.user)
+        )?;
+        for (i, (key, value)) in self.modifiers.iter().enumerate() {
+            let separator = if i == 0 { '?' } else { '&' };
+            write!(f, "{separator}{}={}", encode(key), encode(value))?;
+        }
+        Ok(())
+    }
+}
+
+/// Percent-encode all but the unreserved characters of a string.
+pub(crate) fn encode(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            result.push(byte as char);
+        } else {
+            result.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    result
+}
+
+/// Decode a percent-encoded string, if it's validly encoded UTF-8.
+pub(crate) fn decode(s: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            if !hex.iter().all(u8::is_ascii_hexdigit) {
+                return None;
+            }
+            let hex = std::str::from_utf8(&hex).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}